@@ -0,0 +1,233 @@
+//! Documentation of a set of templates, as Markdown or a standalone HTML page.
+//!
+//! Templates are listed by group and, within a group, under every context they are enabled in.
+
+use crate::emit::TemplateEmitter;
+use crate::template::Template;
+
+/// Heading for templates that are enabled nowhere.
+const NO_CONTEXT: &str = "No context";
+
+pub struct Markdown;
+
+impl TemplateEmitter for Markdown {
+    fn emit_templates(&self, templates: &[Template]) -> String {
+        let mut out = String::from("# Live templates\n");
+        for (group, contexts) in sections(templates) {
+            out.push_str(&format!("\n## {}\n", if group.is_empty() { "Ungrouped".to_string() } else { escape_markdown(&group) }));
+            for (context, templates) in contexts {
+                out.push_str(&format!("\n### {}\n", escape_markdown(&context)));
+                for t in templates {
+                    out.push_str(&format!("\n#### {}\n\n", code_span(&t.name)));
+                    if !t.description.is_empty() {
+                        out.push_str(&format!("{}\n\n", escape_markdown(&t.description)));
+                    }
+                    if !enabled(t).is_empty() {
+                        out.push_str(&format!("Contexts: {}\n\n", escape_markdown(&enabled(t).join(", "))));
+                    }
+                    if !t.variables.is_empty() {
+                        out.push_str("| Variable | Expression | Default value | Stop at |\n|---|---|---|---|\n");
+                        for v in &t.variables {
+                            let cell = |s: &str| if s.is_empty() { String::new() } else { code_span(s).replace('|', "\\|") };
+                            out.push_str(&format!("| {} | {} | {} | {} |\n", escape_markdown(&v.name), cell(&v.expression), cell(&v.default_value),
+                                if v.always_stop_at { "yes" } else { "no" }));
+                        }
+                        out.push('\n');
+                    }
+                    let fence = "`".repeat(longest_run(&t.value, '`').max(2) + 1);
+                    out.push_str(&format!("{}\n{}\n{}\n", fence, t.value, fence));
+                }
+            }
+        }
+        out
+    }
+}
+
+pub struct Html;
+
+impl TemplateEmitter for Html {
+    fn emit_templates(&self, templates: &[Template]) -> String {
+        let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Live templates</title>\n\
+            <style>\nbody { font-family: sans-serif; }\ntable { border-collapse: collapse; }\n\
+            td, th { border: 1px solid #ccc; padding: 2px 6px; text-align: left; }\npre { background: #f4f4f4; padding: 6px; }\n\
+            </style>\n</head>\n<body>\n<h1>Live templates</h1>\n");
+        for (group, contexts) in sections(templates) {
+            out.push_str(&format!("<h2>{}</h2>\n", escape_html(if group.is_empty() { "Ungrouped" } else { &group })));
+            for (context, templates) in contexts {
+                out.push_str(&format!("<h3>{}</h3>\n", escape_html(&context)));
+                for t in templates {
+                    out.push_str(&format!("<h4><code>{}</code></h4>\n", escape_html(&t.name)));
+                    if !t.description.is_empty() {
+                        out.push_str(&format!("<p>{}</p>\n", escape_html(&t.description)));
+                    }
+                    if !enabled(t).is_empty() {
+                        out.push_str(&format!("<p>Contexts: {}</p>\n", escape_html(&enabled(t).join(", "))));
+                    }
+                    if !t.variables.is_empty() {
+                        out.push_str("<table>\n<tr><th>Variable</th><th>Expression</th><th>Default value</th><th>Stop at</th></tr>\n");
+                        for v in &t.variables {
+                            out.push_str(&format!("<tr><td>{}</td><td><code>{}</code></td><td><code>{}</code></td><td>{}</td></tr>\n",
+                                escape_html(&v.name), escape_html(&v.expression), escape_html(&v.default_value),
+                                if v.always_stop_at { "yes" } else { "no" }));
+                        }
+                        out.push_str("</table>\n");
+                    }
+                    out.push_str(&format!("<pre><code>{}</code></pre>\n", escape_html(&t.value)));
+                }
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Templates by group, then by context.
+type Sections<'a> = Vec<(String, Vec<(String, Vec<&'a Template>)>)>;
+
+/// Splits templates by group, then by each enabled context, in order of first appearance.
+fn sections(templates: &[Template]) -> Sections<'_> {
+    let mut sections: Sections = Vec::new();
+    for t in templates {
+        let index = match sections.iter().position(|(group, _)| *group == t.group) {
+            Some(index) => index,
+            None => {
+                sections.push((t.group.clone(), Vec::new()));
+                sections.len() - 1
+            }
+        };
+        let contexts = &mut sections[index].1;
+        let mut names = enabled(t);
+        if names.is_empty() {
+            names.push(NO_CONTEXT);
+        }
+        for name in names {
+            match contexts.iter_mut().find(|(c, _)| c == name) {
+                Some((_, list)) => list.push(t),
+                None => contexts.push((name.to_string(), vec![t])),
+            }
+        }
+    }
+    sections
+}
+
+fn enabled(template: &Template) -> Vec<&str> {
+    template.contexts.iter().filter(|c| c.value).map(|c| c.name.as_str()).collect()
+}
+
+/// Inline code that may itself contain backticks.
+fn code_span(text: &str) -> String {
+    let ticks = "`".repeat(longest_run(text, '`') + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", ticks, text, ticks)
+    } else {
+        format!("{}{}{}", ticks, text, ticks)
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+/// Prose that Markdown shows as written: no HTML tags or entities, table cell breaks, headings or
+/// list items.
+fn escape_markdown(text: &str) -> String {
+    let escaped = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('|', "\\|");
+    let lines: Vec<String> = escaped.split('\n')
+        .map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            match line[indent..].chars().next() {
+                Some('#') | Some('*') => format!("{}\\{}", &line[..indent], &line[indent..]),
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+
+    const XML: &str = r#"<templateSet group="sh">
+  <template name="for" value="for $X$ in ```; do&#10;  $END$&#10;done" description="Loop &lt;items&gt;">
+    <variable name="X" expression="enum(&quot;a|b&quot;)" defaultValue="" alwaysStopAt="true" />
+    <context>
+      <option name="SHELL_SCRIPT" value="true" />
+      <option name="OTHER" value="true" />
+    </context>
+  </template>
+  <template name="x" value="" />
+</templateSet>"#;
+
+    #[test]
+    fn test1() {
+        let (templates, _) = template::read(XML, true).unwrap();
+        let markdown = Markdown.emit_templates(&templates);
+        assert_eq!(markdown, r#"# Live templates
+
+## sh
+
+### SHELL_SCRIPT
+
+#### `for`
+
+Loop &lt;items&gt;
+
+Contexts: SHELL_SCRIPT, OTHER
+
+| Variable | Expression | Default value | Stop at |
+|---|---|---|---|
+| X | `enum("a\|b")` |  | yes |
+
+````
+for $X$ in ```; do
+  $END$
+done
+````
+
+### OTHER
+
+#### `for`
+
+Loop &lt;items&gt;
+
+Contexts: SHELL_SCRIPT, OTHER
+
+| Variable | Expression | Default value | Stop at |
+|---|---|---|---|
+| X | `enum("a\|b")` |  | yes |
+
+````
+for $X$ in ```; do
+  $END$
+done
+````
+
+### No context
+
+#### `x`
+
+```
+
+```
+"#);
+        assert_eq!(escape_markdown("# a|b & <c>\n  * d\ne*"), "\\# a\\|b &amp; &lt;c&gt;\n  \\* d\ne*");
+    }
+
+    #[test]
+    fn test2() {
+        let (templates, _) = template::read(XML, true).unwrap();
+        let html = Html.emit_templates(&templates);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<p>Loop &lt;items&gt;</p>"));
+        assert!(html.contains("<pre><code>for $X$ in ```; do\n  $END$\ndone</code></pre>"));
+        assert!(html.contains("<td><code>enum(&quot;a|b&quot;)</code></td>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
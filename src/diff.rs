@@ -0,0 +1,251 @@
+//! What changed between two versions of a template set, template by template.
+//!
+//! Templates are matched by group and name, so attribute order and escaping don't matter. A
+//! template that disappears while one with the same group and value appears counts as renamed.
+
+use serde_json::{json, Value};
+use std::fmt;
+use crate::escape;
+use crate::template::Template;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    pub added: Vec<Key>,
+    pub removed: Vec<Key>,
+    /// Templates present on both sides, possibly renamed, that differ.
+    pub changed: Vec<Changed>,
+}
+
+/// Identifies a template within a set of template files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub group: String,
+    pub name: String,
+}
+
+impl Key {
+    fn of(template: &Template) -> Key {
+        Key { group: template.group.clone(), name: template.name.clone() }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.group.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}/{}", self.group, self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Changed {
+    pub old: Key,
+    pub new: Key,
+    pub changes: Vec<Change>,
+}
+
+impl Changed {
+    pub fn is_renamed(&self) -> bool {
+        self.old != self.new
+    }
+}
+
+/// One attribute, variable or context option that differs; `None` where it is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// `value`, `description`, `toReformat`, `toShortenFQNames`, another attribute such as
+    /// `deactivated`, `variable NAME`, `variable NAME expression` (or `defaultValue`,
+    /// `alwaysStopAt`, another attribute) or `context NAME`.
+    pub what: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let key = |k: &Key| json!({ "group": k.group, "name": k.name });
+        json!({
+            "added": self.added.iter().map(key).collect::<Vec<_>>(),
+            "removed": self.removed.iter().map(key).collect::<Vec<_>>(),
+            "changed": self.changed.iter().map(|c| json!({
+                "group": c.new.group,
+                "name": c.new.name,
+                "oldName": c.old.name,
+                "changes": c.changes.iter()
+                    .map(|change| json!({ "what": change.what, "old": change.old, "new": change.new }))
+                    .collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Lines starting with `+` for added, `-` for removed and `~` for changed templates, each
+/// change indented below its template.
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for key in &self.added {
+            writeln!(f, "+ {}", key)?;
+        }
+        for key in &self.removed {
+            writeln!(f, "- {}", key)?;
+        }
+        let show = |value: &Option<String>| value.as_ref().map_or("(none)".to_string(), |v| escape::quote(v));
+        for changed in &self.changed {
+            if changed.is_renamed() {
+                writeln!(f, "~ {} (renamed from {})", changed.new, changed.old.name)?;
+            } else {
+                writeln!(f, "~ {}", changed.new)?;
+            }
+            for change in &changed.changes {
+                writeln!(f, "    {}: {} -> {}", change.what, show(&change.old), show(&change.new))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares two versions of a set of templates.
+pub fn diff(old: &[Template], new: &[Template]) -> Diff {
+    let find = |templates: &[Template], t: &Template| templates.iter().position(|o| o.group == t.group && o.name == t.name);
+    let mut added: Vec<&Template> = new.iter().filter(|t| find(old, t).is_none()).collect();
+    let mut removed: Vec<&Template> = old.iter().filter(|t| find(new, t).is_none()).collect();
+
+    let mut pairs: Vec<(&Template, &Template)> = old.iter()
+        .filter_map(|o| find(new, o).map(|i| (o, &new[i])))
+        .collect();
+    let mut i = 0;
+    while i < removed.len() {
+        let renamed = added.iter().position(|a| a.group == removed[i].group && !a.value.is_empty() && a.value == removed[i].value);
+        match renamed {
+            Some(j) => pairs.push((removed.remove(i), added.remove(j))),
+            None => i += 1,
+        }
+    }
+
+    pairs.sort_by_key(|(o, _)| old.iter().position(|t| std::ptr::eq(t, *o)));
+
+    let changed = pairs.into_iter()
+        .map(|(o, n)| Changed { old: Key::of(o), new: Key::of(n), changes: changes(o, n) })
+        .filter(|c| c.is_renamed() || !c.changes.is_empty())
+        .collect();
+    Diff {
+        added: added.into_iter().map(Key::of).collect(),
+        removed: removed.into_iter().map(Key::of).collect(),
+        changed,
+    }
+}
+
+/// What differs between two templates, whatever their names and groups.
+pub fn changes(old: &Template, new: &Template) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut compare = |what: String, old: Option<String>, new: Option<String>| {
+        if old != new {
+            changes.push(Change { what, old, new });
+        }
+    };
+    compare("value".to_string(), Some(old.value.clone()), Some(new.value.clone()));
+    compare("description".to_string(), Some(old.description.clone()), Some(new.description.clone()));
+    compare("toReformat".to_string(), Some(old.to_reformat.to_string()), Some(new.to_reformat.to_string()));
+    compare("toShortenFQNames".to_string(), Some(old.to_shorten_fq_names.to_string()), Some(new.to_shorten_fq_names.to_string()));
+    for (name, o, n) in other_attributes(&old.other_attributes, &new.other_attributes) {
+        compare(name.to_string(), o, n);
+    }
+
+    let mut names: Vec<&str> = old.variables.iter().chain(&new.variables).map(|v| v.name.as_str()).collect();
+    dedup(&mut names);
+    for name in names {
+        let o = old.variables.iter().find(|v| v.name == name);
+        let n = new.variables.iter().find(|v| v.name == name);
+        match (o, n) {
+            (Some(o), Some(n)) => {
+                compare(format!("variable {} expression", name), Some(o.expression.clone()), Some(n.expression.clone()));
+                compare(format!("variable {} defaultValue", name), Some(o.default_value.clone()), Some(n.default_value.clone()));
+                compare(format!("variable {} alwaysStopAt", name), Some(o.always_stop_at.to_string()), Some(n.always_stop_at.to_string()));
+                for (attribute, o, n) in other_attributes(&o.other_attributes, &n.other_attributes) {
+                    compare(format!("variable {} {}", name, attribute), o, n);
+                }
+            }
+            _ => compare(format!("variable {}", name), o.map(|v| v.expression.clone()), n.map(|v| v.expression.clone())),
+        }
+    }
+
+    let mut names: Vec<&str> = old.contexts.iter().chain(&new.contexts).map(|c| c.name.as_str()).collect();
+    dedup(&mut names);
+    for name in names {
+        let value = |t: &Template| t.contexts.iter().find(|c| c.name == name).map(|c| c.value.to_string());
+        compare(format!("context {}", name), value(old), value(new));
+    }
+    changes
+}
+
+/// Every attribute name on either side with its value on each.
+fn other_attributes<'a>(old: &'a [(String, String)], new: &'a [(String, String)]) -> Vec<(&'a str, Option<String>, Option<String>)> {
+    let mut names: Vec<&str> = old.iter().chain(new).map(|(n, _)| n.as_str()).collect();
+    dedup(&mut names);
+    let value = |attributes: &[(String, String)], name: &str| attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone());
+    names.into_iter().map(|name| (name, value(old, name), value(new, name))).collect()
+}
+
+/// Removes repeated names, keeping the first of each.
+fn dedup(names: &mut Vec<&str>) {
+    let mut seen = Vec::new();
+    names.retain(|n| if seen.contains(n) { false } else { seen.push(*n); true });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+
+    #[test]
+    fn test1() {
+        let old = r#"<templateSet group="sh">
+  <template name="keep" value="same" shortcut="TAB" />
+  <template name="gone" value="x" />
+  <template name="old" value="moved" />
+  <template name="edit" value="a&#10;b" description="d">
+    <variable name="X" expression="enum(&quot;a&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="Y" expression="" defaultValue="" alwaysStopAt="true" />
+    <context><option name="SHELL_SCRIPT" value="true" /></context>
+  </template>
+</templateSet>"#;
+        let new = r#"<templateSet group="sh">
+  <template description="d" value="a&#xA;c" name="edit">
+    <variable name="X" expression="enum(&quot;a&quot;, &quot;b&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="Z" expression="date()" defaultValue="" alwaysStopAt="false" />
+    <context><option name="SHELL_SCRIPT" value="false" /><option name="OTHER" value="true" /></context>
+  </template>
+  <template name="keep" value="same" deactivated="true" shortcut="TAB"></template>
+  <template name="new" value="moved" />
+  <template name="fresh" value="" />
+</templateSet>"#;
+        let (old, _) = template::read(old, true).unwrap();
+        let (new, _) = template::read(new, true).unwrap();
+        let diff = diff(&old, &new);
+        assert_eq!(diff.to_string(), r#"+ sh/fresh
+- sh/gone
+~ sh/keep
+    deactivated: (none) -> "true"
+~ sh/new (renamed from old)
+~ sh/edit
+    value: "a\nb" -> "a\nc"
+    variable X expression: "enum(\"a\")" -> "enum(\"a\", \"b\")"
+    variable Y: "" -> (none)
+    variable Z: (none) -> "date()"
+    context SHELL_SCRIPT: "true" -> "false"
+    context OTHER: (none) -> "true"
+"#);
+        let json = diff.to_json();
+        assert_eq!(json["added"], json!([{ "group": "sh", "name": "fresh" }]));
+        assert_eq!(json["changed"][1]["oldName"], "old");
+        assert_eq!(json["changed"][2]["changes"][0], json!({ "what": "value", "old": "a\nb", "new": "a\nc" }));
+        assert!(super::diff(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+//! Finding the live templates of installed JetBrains IDEs on Linux.
+
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::{debug, warn};
+
+/// The configuration directory of one IDE version, e.g. `~/.config/JetBrains/IntelliJIdea2020.1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Product {
+    /// The directory name without its version, e.g. `IntelliJIdea`.
+    pub name: String,
+    /// e.g. `2020.1`; empty if the directory name carries none.
+    pub version: String,
+    pub config_dir: PathBuf,
+}
+
+impl Product {
+    fn new(dir_name: &str, config_dir: PathBuf) -> Product {
+        let versioned = Regex::new(r"^(.*?)(\d{4}\.\d+(?:\.\d+)?)$").unwrap();
+        let (name, version) = match versioned.captures(dir_name) {
+            Some(c) => (c[1].to_string(), c[2].to_string()),
+            None => (dir_name.to_string(), String::new()),
+        };
+        Product { name, version, config_dir }
+    }
+
+    /// `IntelliJIdea2020.1`, as the IDE names its directory.
+    pub fn full_name(&self) -> String {
+        format!("{}{}", self.name, self.version)
+    }
+
+    /// The live template files, `templates/*.xml`, sorted by name.
+    pub fn templates(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(self.config_dir.join("templates"))
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        files.retain(|f| f.is_file() && f.extension().is_some_and(|e| e == "xml"));
+        files.sort();
+        files
+    }
+
+    fn version_key(&self) -> Vec<u32> {
+        self.version.split('.').filter_map(|n| n.parse().ok()).collect()
+    }
+}
+
+/// Where to look; [`Roots::from_env`] fills it in from the environment.
+#[derive(Debug, Clone, Default)]
+pub struct Roots {
+    pub home: Option<PathBuf>,
+    pub xdg_config_home: Option<PathBuf>,
+    /// `JETBRAINS_CONFIG`: a product config directory, or a directory of them.
+    pub jetbrains_config: Option<PathBuf>,
+}
+
+impl Roots {
+    pub fn from_env() -> Roots {
+        let path = |name: &str| env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+        Roots {
+            home: path("HOME"),
+            xdg_config_home: path("XDG_CONFIG_HOME"),
+            jetbrains_config: path("JETBRAINS_CONFIG"),
+        }
+    }
+}
+
+/// Lists the IDE configuration directories found from `roots`, sorted by name and version.
+///
+/// `JETBRAINS_CONFIG` replaces the search entirely. Otherwise these layouts are searched:
+/// - `$XDG_CONFIG_HOME/JetBrains/*` (2020.1 and later; `~/.config` by default)
+/// - `~/.<Product><Version>/config` (before 2020.1)
+/// - `idea.config.path` set in `idea.properties` of IDEs installed by the Toolbox App, under
+///   `~/.local/share/JetBrains/Toolbox/apps`
+pub fn products(roots: &Roots) -> Vec<Product> {
+    let mut products = Vec::new();
+    if let Some(config) = &roots.jetbrains_config {
+        if config.join("templates").is_dir() {
+            products.push(Product::new(&file_name(config), config.clone()));
+        } else {
+            products.extend(subdirs(config).into_iter().map(|dir| Product::new(&file_name(&dir), dir)));
+        }
+        return sorted(products);
+    }
+
+    let xdg = roots.xdg_config_home.clone().or_else(|| roots.home.as_ref().map(|h| h.join(".config")));
+    if let Some(xdg) = xdg {
+        products.extend(subdirs(&xdg.join("JetBrains")).into_iter().map(|dir| Product::new(&file_name(&dir), dir)));
+    }
+    if let Some(home) = &roots.home {
+        for dir in subdirs(home) {
+            let name = file_name(&dir);
+            if name.starts_with('.') && dir.join("config").is_dir() && !Product::new(&name[1..], PathBuf::new()).version.is_empty() {
+                products.push(Product::new(&name[1..], dir.join("config")));
+            }
+        }
+        products.extend(toolbox_products(home, &home.join(".local/share/JetBrains/Toolbox/apps")));
+    }
+    products.retain(|p| p.config_dir.is_dir());
+    let mut products = sorted(products);
+    products.dedup_by(|a, b| a.config_dir == b.config_dir);
+    products
+}
+
+/// Products whose name or full name matches `query`, case-insensitively.
+///
+/// A full name (`PyCharm2020.1`) selects that version; a bare product name (`PyCharm`) selects
+/// the newest version of it.
+pub fn find(products: &[Product], query: &str) -> Vec<Product> {
+    let query = query.to_lowercase();
+    let exact: Vec<Product> = products.iter().filter(|p| p.full_name().to_lowercase() == query).cloned().collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    products.iter().filter(|p| p.name.to_lowercase() == query).max_by_key(|p| p.version_key()).cloned().into_iter().collect()
+}
+
+fn toolbox_products(home: &Path, apps: &Path) -> Vec<Product> {
+    let mut products = Vec::new();
+    // apps/<App>/ch-<n>/<build>/bin/idea.properties
+    for app in subdirs(apps) {
+        for channel in subdirs(&app) {
+            for build in subdirs(&channel) {
+                let properties = fs::read_to_string(build.join("bin").join("idea.properties")).unwrap_or_default();
+                if let Some(dir) = config_path(&properties, home, &build) {
+                    debug!("Toolbox config path {}", dir.display());
+                    products.push(Product::new(&file_name(&dir), dir));
+                }
+            }
+        }
+    }
+    products
+}
+
+/// The `idea.config.path` set in the text of an `idea.properties` file, with `${user.home}`,
+/// `${idea.home.path}` (the product's installation directory) and properties set earlier in the
+/// file expanded.
+fn config_path(properties: &str, home: &Path, install: &Path) -> Option<PathBuf> {
+    let mut values = vec![
+        ("user.home".to_string(), home.display().to_string()),
+        ("idea.home.path".to_string(), install.display().to_string()),
+    ];
+    let mut config_path = None;
+    for line in properties.lines().map(str::trim_start) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        // The key ends at the first unescaped `=`, `:` or white space, which may be followed by
+        // more white space and one `=` or `:`.
+        let mut escaped = false;
+        let split = line.find(|c: char| {
+            let end = !escaped && (c == '=' || c == ':' || c.is_whitespace());
+            escaped = !escaped && c == '\\';
+            end
+        }).unwrap_or(line.len());
+        let key = unescape(&line[..split]);
+        let rest = line[split..].trim_start();
+        let value = rest.strip_prefix(['=', ':']).unwrap_or(rest).trim();
+        let value = match expand(&unescape(value), &values) {
+            Ok(value) => value,
+            Err(name) => {
+                warn!("Can't expand ${{{}}} in {}", name, line);
+                continue;
+            }
+        };
+        if key == "idea.config.path" {
+            config_path = Some(PathBuf::from(&value));
+        }
+        values.push((key, value));
+    }
+    config_path
+}
+
+/// Replaces every `${name}` in `value`; an unknown name is the error.
+fn expand(value: &str, values: &[(String, String)]) -> std::result::Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(i) = rest.find("${") {
+        expanded.push_str(&rest[..i]);
+        let end = rest[i..].find('}').map(|end| i + end).ok_or_else(|| rest[i + 2..].to_string())?;
+        let name = &rest[i + 2..end];
+        match values.iter().rev().find(|(n, _)| n == name) {
+            Some((_, v)) => expanded.push_str(v),
+            None => return Err(name.to_string()),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Drops the backslashes of a `.properties` value, as in `C\:\\Users`.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        unescaped.extend(if c == '\\' { chars.next() } else { Some(c) });
+    }
+    unescaped
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect())
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn sorted(mut products: Vec<Product>) -> Vec<Product> {
+    products.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version_key().cmp(&b.version_key())));
+    products
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn test1() {
+        let home = env::temp_dir().join("t2e-discover-test1");
+        let _ = fs::remove_dir_all(&home);
+        touch(&home.join(".config/JetBrains/PyCharm2020.1/templates/Python.xml"));
+        touch(&home.join(".config/JetBrains/PyCharm2020.1/templates/notes.txt"));
+        touch(&home.join(".config/JetBrains/PyCharm2019.3/templates/a.xml"));
+        touch(&home.join(".IntelliJIdea2019.3/config/templates/shell.xml"));
+        touch(&home.join(".cache/templates/x.xml"));
+        fs::create_dir_all(home.join("toolbox-config/CLion2020.1/templates")).unwrap();
+        let properties = home.join(".local/share/JetBrains/Toolbox/apps/CLion/ch-0/201.6668/bin/idea.properties");
+        touch(&properties);
+        fs::write(&properties, format!("# custom\nidea.config.path={}\n", home.join("toolbox-config/CLion2020.1").display())).unwrap();
+
+        let roots = Roots { home: Some(home.clone()), ..Default::default() };
+        let found = products(&roots);
+        let names: Vec<String> = found.iter().map(|p| p.full_name()).collect();
+        assert_eq!(names, vec!["CLion2020.1", "IntelliJIdea2019.3", "PyCharm2019.3", "PyCharm2020.1"]);
+        assert_eq!(found[1].config_dir, home.join(".IntelliJIdea2019.3/config"));
+        assert_eq!(found[3].templates(), vec![home.join(".config/JetBrains/PyCharm2020.1/templates/Python.xml")]);
+
+        assert_eq!(find(&found, "pycharm").iter().map(|p| p.full_name()).collect::<Vec<_>>(), vec!["PyCharm2020.1"]);
+        assert_eq!(find(&found, "PyCharm2019.3").len(), 1);
+        assert!(find(&found, "Rider").is_empty());
+
+        let roots = Roots { jetbrains_config: Some(home.join(".config/JetBrains")), ..roots };
+        assert_eq!(products(&roots).len(), 2);
+        let roots = Roots { jetbrains_config: Some(home.join(".IntelliJIdea2019.3/config")), ..roots };
+        assert_eq!(products(&roots).len(), 1);
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test2() {
+        let home = env::temp_dir().join("t2e-discover-test2");
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(home.join(".config/JetBrains/WebStorm2021.1")).unwrap();
+        let properties = home.join(".local/share/JetBrains/Toolbox/apps/WebStorm/ch-0/211.6693/bin/idea.properties");
+        touch(&properties);
+        fs::write(&properties, "#---\n# idea.config.path=${user.home}/.WebStorm/config\n\
+            idea.config.path = ${user.home}/.config/JetBrains/WebStorm2021.1\n").unwrap();
+
+        let found = products(&Roots { home: Some(home.clone()), ..Default::default() });
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].full_name(), "WebStorm2021.1");
+        assert_eq!(found[0].config_dir, home.join(".config/JetBrains/WebStorm2021.1"));
+        fs::remove_dir_all(&home).unwrap();
+
+        let (home, install) = (Path::new("/home/me"), Path::new("/opt/idea"));
+        let path = |properties: &str| config_path(properties, home, install);
+        assert_eq!(path("base=${idea.home.path}/c\nidea.config.path:${base}/IDEA\\ 2021"), Some(PathBuf::from("/opt/idea/c/IDEA 2021")));
+        assert_eq!(path("idea.config.path=${java.io.tmpdir}/x"), None);
+        assert_eq!(path("  ! idea.config.path=/x"), None);
+        assert_eq!(path("idea.config.path ${user.home}/c"), Some(PathBuf::from("/home/me/c")));
+        assert_eq!(path("idea.config.path  :  /x = y"), Some(PathBuf::from("/x = y")));
+        assert_eq!(path("idea.config\\ path=/x"), None);
+    }
+}
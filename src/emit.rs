@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+use crate::expr::Expr;
+use crate::template::{self, ContextOption, Template, Variable};
+
+/// Renders the extracted entries into the final text.
+pub trait Emitter {
+    fn emit(&self, items: &[String]) -> String;
+}
+
+/// Renders whole templates, for exporting them rather than a list of entries.
+pub trait TemplateEmitter {
+    fn emit_templates(&self, templates: &[Template]) -> String;
+}
+
+/// Renders whole templates into several files, for editors that keep snippets per language.
+pub trait FilesEmitter {
+    /// Paths relative to the output directory and their contents.
+    fn emit_files(&self, templates: &[Template]) -> Vec<(PathBuf, String)>;
+}
+
+/// A JetBrains live template `enum("a", "b", ...)` expression.
+pub struct JetBrainsEnum;
+
+impl Emitter for JetBrainsEnum {
+    fn emit(&self, items: &[String]) -> String {
+        Expr::enumeration(items).to_string()
+    }
+}
+
+/// One entry per line, unescaped.
+pub struct PlainLines;
+
+impl Emitter for PlainLines {
+    fn emit(&self, items: &[String]) -> String {
+        items.join("\n")
+    }
+}
+
+/// A complete `<template>` element whose only variable is the `enum()` of the entries.
+pub struct TemplateXml {
+    pub name: String,
+    pub description: String,
+    /// The variable name; the template value is `$VARIABLE$`.
+    pub variable: String,
+    /// Contexts the template is enabled in; the IDE needs at least one.
+    pub contexts: Vec<String>,
+}
+
+impl Default for TemplateXml {
+    fn default() -> TemplateXml {
+        TemplateXml {
+            name: String::new(),
+            description: String::new(),
+            variable: "X".to_string(),
+            contexts: vec!["OTHER".to_string()],
+        }
+    }
+}
+
+impl TemplateXml {
+    /// A template with this name, description and contexts around the given value and variables.
+    pub fn template(&self, value: String, variables: Vec<Variable>) -> Template {
+        Template {
+            name: self.name.clone(),
+            value,
+            description: self.description.clone(),
+            variables,
+            contexts: self.contexts.iter().map(|name| ContextOption { name: name.clone(), value: true }).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Emitter for TemplateXml {
+    fn emit(&self, items: &[String]) -> String {
+        let variable = Variable {
+            name: self.variable.clone(),
+            expression: JetBrainsEnum.emit(items),
+            default_value: String::new(),
+            always_stop_at: true,
+            ..Default::default()
+        };
+        self.template(format!("${}$", self.variable), vec![variable]).to_xml()
+    }
+}
+
+/// JetBrains template XML, one `<templateSet>` per group.
+pub struct TemplateSets;
+
+impl TemplateEmitter for TemplateSets {
+    fn emit_templates(&self, templates: &[Template]) -> String {
+        template::group_by_set(templates.to_vec()).iter()
+            .map(|(group, templates)| template::set_to_xml(group, templates))
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        let items = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(PlainLines.emit(&items), "a\nb");
+        assert_eq!(JetBrainsEnum.emit(&items), "enum(\"a\", \"b\")");
+    }
+
+    #[test]
+    fn test2() {
+        let items = vec![r"C:\temp\".to_string(), "two\nlines".to_string()];
+        assert_eq!(JetBrainsEnum.emit(&items), r#"enum("C:\\temp\\", "two\nlines")"#);
+    }
+
+    #[test]
+    fn test3() {
+        let items = vec!["a".to_string(), "<b>".to_string()];
+        let emitter = TemplateXml { name: "pick".to_string(), description: "Pick \"one\"".to_string(), ..Default::default() };
+        assert_eq!(emitter.emit(&items), r#"<template name="pick" value="$X$" description="Pick &quot;one&quot;" toReformat="false" toShortenFQNames="false">
+  <variable name="X" expression="enum(&quot;a&quot;, &quot;&lt;b&gt;&quot;)" defaultValue="" alwaysStopAt="true"/>
+  <context>
+    <option name="OTHER" value="true"/>
+  </context>
+</template>"#);
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use crate::eval::EvalError;
+use crate::expr::ParseError;
+
+pub type Result<T> = std::result::Result<T, T2eError>;
+
+/// A line and column in the input, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Converts a byte offset into `text` to a line and column; columns count characters.
+    pub fn of(text: &str, offset: usize) -> Position {
+        let mut offset = offset.min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub enum T2eError {
+    Clipboard(String),
+    /// Reading or writing `path` (or `stdin`/`stdout`) failed.
+    Io { path: String, source: io::Error },
+    /// An input pattern that matches no file.
+    NoInput(String),
+    /// No configuration directory of a JetBrains IDE matches the name.
+    NoIde(String),
+    InvalidUtf8 { path: String, offset: usize },
+    Xml { position: Position, template: Option<String>, message: String },
+    MissingAttribute { position: Position, element: String, attribute: String },
+    Json { position: Position, message: String },
+    Expression(ParseError),
+    Eval(EvalError),
+    /// `lint` found this many problems at or above the failing severity.
+    Lint(usize),
+    /// `fmt --check` found files not in canonical form.
+    Unformatted(Vec<String>),
+    /// Options that can't be honoured together or for this input.
+    Usage(String),
+}
+
+impl T2eError {
+    /// The process exit code for this kind of error, so that scripts can tell them apart.
+    ///
+    /// | code | error                      |
+    /// |------|----------------------------|
+    /// | 2    | input not found            |
+    /// | 3    | clipboard unavailable      |
+    /// | 4    | I/O error                  |
+    /// | 5    | invalid UTF-8              |
+    /// | 6    | XML syntax error           |
+    /// | 7    | missing attribute          |
+    /// | 8    | invalid expression         |
+    /// | 9    | lint problems found        |
+    /// | 10   | files need formatting      |
+    /// | 11   | JSON syntax error          |
+    /// | 12   | unusable options           |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            T2eError::NoInput(_) | T2eError::NoIde(_) => 2,
+            T2eError::Clipboard(_) => 3,
+            T2eError::Io { .. } => 4,
+            T2eError::InvalidUtf8 { .. } => 5,
+            T2eError::Xml { .. } => 6,
+            T2eError::MissingAttribute { .. } => 7,
+            T2eError::Expression(_) | T2eError::Eval(_) => 8,
+            T2eError::Lint(_) => 9,
+            T2eError::Unformatted(_) => 10,
+            T2eError::Json { .. } => 11,
+            T2eError::Usage(_) => 12,
+        }
+    }
+
+    pub fn io(path: &str, source: io::Error) -> T2eError {
+        T2eError::Io { path: path.to_string(), source }
+    }
+}
+
+impl fmt::Display for T2eError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            T2eError::Clipboard(e) => write!(f, "Clipboard unavailable: {}", e),
+            T2eError::Io { path, source } => write!(f, "{}: {}", path, source),
+            T2eError::NoInput(pattern) => write!(f, "No file matches {}", pattern),
+            T2eError::NoIde(name) => write!(f, "No JetBrains IDE configuration found for {}", name),
+            T2eError::InvalidUtf8 { path, offset } => write!(f, "{}: invalid UTF-8 at byte {}", path, offset),
+            T2eError::Xml { position, template: Some(name), message } =>
+                write!(f, "XML error at {} in template \"{}\": {}", position, name, message),
+            T2eError::Xml { position, template: None, message } => write!(f, "XML error at {}: {}", position, message),
+            T2eError::MissingAttribute { position, element, attribute } =>
+                write!(f, "<{}> at {} has no {} attribute", element, position, attribute),
+            T2eError::Json { position, message } => write!(f, "JSON error at {}: {}", position, message),
+            T2eError::Expression(e) => write!(f, "Invalid expression: {}", e),
+            T2eError::Eval(e) => write!(f, "{}", e),
+            T2eError::Lint(count) => write!(f, "{} problem(s) found", count),
+            T2eError::Unformatted(files) => write!(f, "Not formatted: {}", files.join(", ")),
+            T2eError::Usage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for T2eError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            T2eError::Io { source, .. } => Some(source),
+            T2eError::Expression(e) => Some(e),
+            T2eError::Eval(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for T2eError {
+    fn from(e: ParseError) -> T2eError {
+        T2eError::Expression(e)
+    }
+}
+
+impl From<EvalError> for T2eError {
+    fn from(e: EvalError) -> T2eError {
+        T2eError::Eval(e)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        let text = "ab\ncdé\nf";
+        assert_eq!(Position::of(text, 0), Position { line: 1, column: 1 });
+        assert_eq!(Position::of(text, 3), Position { line: 2, column: 1 });
+        assert_eq!(Position::of(text, 7), Position { line: 2, column: 4 });
+        assert_eq!(Position::of(text, 100), Position { line: 3, column: 2 });
+    }
+}
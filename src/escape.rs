@@ -0,0 +1,116 @@
+//! String literals of the JetBrains live template expression language.
+//!
+//! The IDE lexes a literal as `"` followed by any characters except `"`, `\` and line breaks,
+//! or a backslash followed by any character but a line break, up to the closing `"`. The body
+//! is then unescaped with `StringUtil.unescapeStringCharacters`: `\b`, `\t`, `\n`, `\f`, `\r`
+//! and `\uXXXX` are decoded, and any other escaped character stands for itself.
+
+/// Escapes `s` so that it can be placed between double quotes in a template expression.
+pub fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Decodes the body of a string literal, i.e. the text between the quotes.
+pub fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => result.push('\u{8}'),
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('f') => result.push('\u{c}'),
+            Some('r') => result.push('\r'),
+            Some('u') => {
+                let hex: String = chars.clone().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 4).and_then(std::char::from_u32) {
+                    Some(decoded) => {
+                        result.push(decoded);
+                        chars.nth(3);
+                    }
+                    None => result.push('u'),
+                }
+            }
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Lexes a string literal at the start of `input`.
+///
+/// Returns the decoded value and the rest of the input after the closing quote, or `None` if
+/// `input` does not start with a complete literal.
+pub fn parse_literal(input: &str) -> Option<(String, &str)> {
+    if !input.starts_with('"') {
+        return None;
+    }
+    let mut escaped = false;
+    for (i, c) in input.char_indices().skip(1) {
+        match c {
+            '\n' | '\r' => return None,
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some((unescape(&input[1..i]), &input[i + 1..])),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Wraps `s` in double quotes, escaped.
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", escape(s))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        assert_eq!(escape(r#"C:\temp\"x""#), r#"C:\\temp\\\"x\""#);
+        assert_eq!(escape("a\nb\tc\u{1}"), r"a\nb\tc\u0001");
+    }
+
+    #[test]
+    fn test2() {
+        let samples = vec![
+            "", "plain", "\"", "\\", "\\\"", "ends with \\", "C:\\Users\\me", r"^\d+\s*$",
+            "line 1\nline 2\r\n", "tab\there", "\u{0}\u{7}\u{8}\u{c}\u{1b}\u{7f}\u{85}", "ünïcödé ✓ 😀",
+        ];
+        for s in samples {
+            let literal = quote(s);
+            assert_eq!(parse_literal(&literal), Some((s.to_string(), "")), "{}", literal);
+        }
+    }
+
+    #[test]
+    fn test3() {
+        assert_eq!(parse_literal(r#""a\"b", "c")"#), Some(("a\"b".to_string(), r#", "c")"#)));
+        assert_eq!(parse_literal(r#""\'\q\u0041\u00zz""#), Some(("'qAu00zz".to_string(), "")));
+        assert_eq!(parse_literal(r#""unterminated\""#), None);
+        assert_eq!(parse_literal("\"broken\nline\""), None);
+        assert_eq!(parse_literal("no quote"), None);
+    }
+}
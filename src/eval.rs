@@ -0,0 +1,330 @@
+//! Offline evaluation of live template variable expressions.
+//!
+//! Only the functions that do not need an IDE (no PSI, no editor, no completion) are
+//! evaluated; the others are recognised so that they can be reported as such.
+
+use chrono::{Local, NaiveDateTime};
+use regex::Regex;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use crate::escape;
+use crate::expr::Expr;
+
+/// Everything an expression may depend on besides its own text.
+pub struct Context {
+    /// Values of the other template variables, by name.
+    pub variables: HashMap<String, String>,
+    pub now: NaiveDateTime,
+    pub user: String,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context {
+            variables: HashMap::new(),
+            now: Local::now().naive_local(),
+            user: env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// A real template function that can only be computed inside the IDE.
+    NeedsIde(String),
+    Unknown(String),
+    Arity { function: String, found: usize },
+    InvalidRegex(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::NeedsIde(name) => write!(f, "{}() can only be evaluated in the IDE", name),
+            EvalError::Unknown(name) => write!(f, "Unknown function {}()", name),
+            EvalError::Arity { function, found } => write!(f, "{}() does not take {} argument(s)", function, found),
+            EvalError::InvalidRegex(e) => write!(f, "Invalid regular expression: {}", e),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// Template functions that depend on the code being edited.
+const IDE_FUNCTIONS: &[&str] = &[
+    "anonymousSuper", "arrayVariable", "castToLeftSideType", "className", "classNameComplete",
+    "clipboard", "complete", "completeSmart", "componentTypeOf", "currentPackage",
+    "descendantClassesEnum", "expectedType", "fileName", "fileNameWithoutExtension", "filePath",
+    "fileRelativePath", "groovyScript", "guessElementType", "iterableComponentType",
+    "iterableVariable", "lineNumber", "methodName", "methodParameters", "methodReturnType",
+    "qualifiedClassName", "rightSideType", "subtypes", "suggestFirstVariableName",
+    "suggestIndexName", "suggestVariableName", "typeOfVariable", "variableOfType",
+];
+
+/// Allowed argument counts of the functions evaluated here, as `(min, max)`.
+fn arity(name: &str) -> Option<(usize, usize)> {
+    match name {
+        "enum" => Some((1, usize::MAX)),
+        "concat" => Some((0, usize::MAX)),
+        "capitalize" | "decapitalize" | "camelCase" | "snakeCase" | "lowercaseAndDash"
+        | "capitalizeAndUnderscore" | "underscoresToCamelCase" | "underscoresToSpaces"
+        | "spacesToUnderscores" | "firstWord" | "escapeString" => Some((1, 1)),
+        "substringBefore" => Some((2, 2)),
+        "regularExpression" => Some((3, 3)),
+        "date" | "time" => Some((0, 1)),
+        "user" => Some((0, 0)),
+        _ => None,
+    }
+}
+
+/// Checks every call in `expr` against the known functions, without evaluating anything.
+pub fn validate(expr: &Expr) -> Vec<EvalError> {
+    let mut problems = Vec::new();
+    check(expr, &mut problems);
+    problems
+}
+
+fn check(expr: &Expr, problems: &mut Vec<EvalError>) {
+    if let Expr::Call(name, args) = expr {
+        if let Err(e) = check_call(name, args.len()) {
+            problems.push(e);
+        }
+        for arg in args {
+            check(arg, problems);
+        }
+    }
+}
+
+fn check_call(name: &str, found: usize) -> Result<(), EvalError> {
+    match arity(name) {
+        Some((min, max)) if found < min || found > max => Err(EvalError::Arity { function: name.to_string(), found }),
+        Some(_) => Ok(()),
+        None if IDE_FUNCTIONS.contains(&name) => Err(EvalError::NeedsIde(name.to_string())),
+        None => Err(EvalError::Unknown(name.to_string())),
+    }
+}
+
+/// Computes the value the IDE would insert for `expr`.
+///
+/// `enum()` yields its first choice, which is what the IDE preselects; unknown variables are
+/// empty, as they are in the editor before the user fills them in.
+pub fn eval(expr: &Expr, context: &Context) -> Result<String, EvalError> {
+    let (name, args) = match expr {
+        Expr::Str(s) => return Ok(s.clone()),
+        Expr::Var(name) => return Ok(context.variables.get(name).cloned().unwrap_or_default()),
+        Expr::Call(name, args) => (name.as_str(), args),
+    };
+    check_call(name, args.len())?;
+    let args = args.iter().map(|arg| eval(arg, context)).collect::<Result<Vec<String>, EvalError>>()?;
+    let arg = |i: usize| args.get(i).map(String::as_str).unwrap_or("");
+    Ok(match name {
+        "enum" => arg(0).to_string(),
+        "concat" => args.concat(),
+        "capitalize" => capitalize(arg(0)),
+        "decapitalize" => decapitalize(arg(0)),
+        "camelCase" => camel_case(&words(arg(0))),
+        "snakeCase" => lowercase(&words(arg(0))).join("_"),
+        "lowercaseAndDash" => lowercase(&words(arg(0))).join("-"),
+        "capitalizeAndUnderscore" => words(arg(0)).iter().map(|w| w.to_uppercase()).collect::<Vec<_>>().join("_"),
+        "underscoresToCamelCase" => camel_case(&arg(0).split('_').filter(|w| !w.is_empty()).map(String::from).collect::<Vec<_>>()),
+        "underscoresToSpaces" => arg(0).replace('_', " "),
+        "spacesToUnderscores" => arg(0).replace(' ', "_"),
+        "firstWord" => arg(0).split_whitespace().next().unwrap_or("").to_string(),
+        "escapeString" => escape::escape(arg(0)),
+        "substringBefore" => arg(0).find(arg(1)).map_or(arg(0), |i| &arg(0)[..i]).to_string(),
+        "regularExpression" => {
+            let regex = Regex::new(arg(1)).map_err(|e| EvalError::InvalidRegex(e.to_string()))?;
+            regex.replace_all(arg(0), arg(2)).into_owned()
+        }
+        "date" => context.now.format(&date_format(if args.is_empty() { "MMM d, yyyy" } else { arg(0) })).to_string(),
+        "time" => context.now.format(&date_format(if args.is_empty() { "h:mm a" } else { arg(0) })).to_string(),
+        "user" => context.user.clone(),
+        _ => unreachable!("check_call accepted {}", name),
+    })
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn decapitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn lowercase(words: &[String]) -> Vec<String> {
+    words.iter().map(|w| w.to_lowercase()).collect()
+}
+
+fn camel_case(words: &[String]) -> String {
+    words.iter().enumerate()
+        .map(|(i, w)| if i == 0 { w.to_lowercase() } else { capitalize(&w.to_lowercase()) })
+        .collect()
+}
+
+/// Splits an identifier-ish string into words at separators and camel humps:
+/// `"HTTPServer_port-no"` gives `HTTP`, `Server`, `port`, `no`.
+fn words(s: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    for chunk in s.split(|c: char| !c.is_alphanumeric()).filter(|c| !c.is_empty()) {
+        let chars: Vec<char> = chunk.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let hump = i > 0 && c.is_uppercase() && (!chars[i - 1].is_uppercase()
+                || chars.get(i + 1).is_some_and(|next| next.is_lowercase()));
+            if hump && !word.is_empty() {
+                result.push(word.clone());
+                word.clear();
+            }
+            word.push(c);
+        }
+        result.push(word);
+    }
+    result
+}
+
+/// Translates a Java `SimpleDateFormat` pattern into a chrono format string.
+fn date_format(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let mut run = 1;
+        while i + run < chars.len() && chars[i + run] == c {
+            run += 1;
+        }
+        if c == '\'' {
+            // '' is a literal quote, 'text' is literal text.
+            if run >= 2 {
+                result.push('\'');
+                i += 2;
+                continue;
+            }
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if chars.get(i + 1) == Some(&'\'') {
+                        result.push('\'');
+                        i += 2;
+                        continue;
+                    }
+                    i += 1;
+                    break;
+                }
+                push_literal(&mut result, chars[i]);
+                i += 1;
+            }
+            continue;
+        }
+        let spec = match (c, run) {
+            ('y', 2) => "%y",
+            ('y', _) => "%Y",
+            ('M', 1) => "%-m",
+            ('M', 2) => "%m",
+            ('M', 3) => "%b",
+            ('M', _) => "%B",
+            ('d', 1) => "%-d",
+            ('d', _) => "%d",
+            ('H', 1) => "%-H",
+            ('H', _) => "%H",
+            ('h', 1) => "%-I",
+            ('h', _) => "%I",
+            ('m', 1) => "%-M",
+            ('m', _) => "%M",
+            ('s', 1) => "%-S",
+            ('s', _) => "%S",
+            ('S', _) => "%3f",
+            ('a', _) => "%p",
+            ('E', 1..=3) => "%a",
+            ('E', _) => "%A",
+            _ => "",
+        };
+        if spec.is_empty() {
+            for _ in 0..run {
+                push_literal(&mut result, c);
+            }
+        } else {
+            result.push_str(spec);
+        }
+        i += run;
+    }
+    result
+}
+
+fn push_literal(format: &mut String, c: char) {
+    if c == '%' {
+        format.push_str("%%");
+    } else {
+        format.push(c);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::expr::parse;
+
+    fn context() -> Context {
+        let mut context = Context::new();
+        context.now = NaiveDate::from_ymd(2020, 1, 9).and_hms(14, 5, 7);
+        context.user = "yangshuai".to_string();
+        context.variables.insert("NAME".to_string(), "user_account-id".to_string());
+        context
+    }
+
+    fn run(text: &str) -> Result<String, EvalError> {
+        eval(&parse(text).unwrap(), &context())
+    }
+
+    #[test]
+    fn test1() {
+        assert_eq!(run("enum(\"first\", \"second\")"), Ok("first".to_string()));
+        assert_eq!(run("camelCase(NAME)"), Ok("userAccountId".to_string()));
+        assert_eq!(run("capitalize(camelCase(NAME))"), Ok("UserAccountId".to_string()));
+        assert_eq!(run("snakeCase(\"HTTPServerPort\")"), Ok("http_server_port".to_string()));
+        assert_eq!(run("capitalizeAndUnderscore(\"fooBar\")"), Ok("FOO_BAR".to_string()));
+        assert_eq!(run("lowercaseAndDash(\"FooBar baz\")"), Ok("foo-bar-baz".to_string()));
+        assert_eq!(run("underscoresToCamelCase(\"foo_bar\")"), Ok("fooBar".to_string()));
+        assert_eq!(run("concat(decapitalize(\"Foo\"), \"-\", UNSET, user())"), Ok("foo-yangshuai".to_string()));
+        assert_eq!(run("substringBefore(\"a.b.c\", \".\")"), Ok("a".to_string()));
+        assert_eq!(run("regularExpression(NAME, \"[_-]\", \".\")"), Ok("user.account.id".to_string()));
+        assert_eq!(run("escapeString(\"a\\\"b\")"), Ok("a\\\"b".to_string()));
+    }
+
+    #[test]
+    fn test2() {
+        assert_eq!(run("date(\"yyyy-MM-dd'T'HH:mm:ss\")"), Ok("2020-01-09T14:05:07".to_string()));
+        assert_eq!(run("date(\"d MMM yy, EEEE\")"), Ok("9 Jan 20, Thursday".to_string()));
+        assert_eq!(run("date()"), Ok("Jan 9, 2020".to_string()));
+        assert_eq!(run("time()"), Ok("2:05 PM".to_string()));
+        assert_eq!(run("time(\"h 'o''clock' a, 100%\")"), Ok("2 o'clock PM, 100%".to_string()));
+    }
+
+    #[test]
+    fn test3() {
+        assert_eq!(run("className()"), Err(EvalError::NeedsIde("className".to_string())));
+        assert_eq!(run("capitalize()"), Err(EvalError::Arity { function: "capitalize".to_string(), found: 0 }));
+        assert_eq!(validate(&parse("concat(foo(), groovyScript(\"1\"), capitalize(\"a\"))").unwrap()), vec![
+            EvalError::Unknown("foo".to_string()),
+            EvalError::NeedsIde("groovyScript".to_string()),
+        ]);
+    }
+}
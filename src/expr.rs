@@ -0,0 +1,181 @@
+//! Syntax tree and parser for live template variable expressions.
+//!
+//! An expression is a string literal, a reference to another template variable, or a call of
+//! a template function: `capitalize(concat(NAME, "Impl"))`.
+
+use std::error::Error;
+use std::fmt;
+use crate::escape;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Str(String),
+    Var(String),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    /// `enum("a", "b", ...)`, the expression t2e exists to generate.
+    pub fn enumeration(items: &[String]) -> Expr {
+        Expr::Call("enum".to_string(), items.iter().map(|e| Expr::Str(e.clone())).collect())
+    }
+
+    /// Returns the values of an `enum()` call whose arguments are all string literals.
+    pub fn as_enumeration(&self) -> Option<Vec<String>> {
+        match self {
+            Expr::Call(name, args) if name == "enum" => args.iter()
+                .map(|arg| match arg {
+                    Expr::Str(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Str(s) => write!(f, "{}", escape::quote(s)),
+            Expr::Var(name) => write!(f, "{}", name),
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset into the parsed text.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.offset)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parses a whole expression; anything but whitespace after it is an error.
+pub fn parse(text: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser { text, pos: 0 };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("Unexpected text after expression"));
+    }
+    Ok(expr)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError { offset: self.pos, message: message.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        if rest.starts_with('"') {
+            let (value, after) = escape::parse_literal(rest).ok_or_else(|| self.error("Unterminated string literal"))?;
+            self.pos += rest.len() - after.len();
+            return Ok(Expr::Str(value));
+        }
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("Expected a string, variable or function call"));
+        }
+        let name = rest[..len].to_string();
+        self.pos += len;
+        if !self.eat('(') {
+            return Ok(Expr::Var(name));
+        }
+        let mut args = Vec::new();
+        if self.eat(')') {
+            return Ok(Expr::Call(name, args));
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.eat(')') {
+                return Ok(Expr::Call(name, args));
+            }
+            if !self.eat(',') {
+                return Err(self.error("Expected ',' or ')'"));
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        let expr = parse(r#" capitalize( concat(NAME ,"Impl\"") ) "#).unwrap();
+        assert_eq!(expr, Expr::Call("capitalize".to_string(), vec![
+            Expr::Call("concat".to_string(), vec![Expr::Var("NAME".to_string()), Expr::Str("Impl\"".to_string())]),
+        ]));
+        assert_eq!(expr.to_string(), r#"capitalize(concat(NAME, "Impl\""))"#);
+        assert_eq!(parse(&expr.to_string()), Ok(expr));
+    }
+
+    #[test]
+    fn test2() {
+        let items = vec!["a".to_string(), r"b\".to_string()];
+        let expr = Expr::enumeration(&items);
+        assert_eq!(expr.to_string(), r#"enum("a", "b\\")"#);
+        assert_eq!(parse(&expr.to_string()).unwrap().as_enumeration(), Some(items));
+        assert_eq!(parse("enum(\"a\", NAME)").unwrap().as_enumeration(), None);
+        assert_eq!(parse("date()"), Ok(Expr::Call("date".to_string(), vec![])));
+        assert_eq!(parse("enum ( \"a\" ,\"b\" )").unwrap().as_enumeration(), Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(parse("enum()").unwrap().as_enumeration(), Some(vec![]));
+        assert_eq!(parse("concat(\"a\")").unwrap().as_enumeration(), None);
+        assert_eq!(parse("enum(\"a\") x").unwrap_err().offset, 10);
+    }
+
+    #[test]
+    fn test3() {
+        assert_eq!(parse("concat(\"a\" \"b\")").unwrap_err().offset, 11);
+        assert_eq!(parse("concat(\"a\"").unwrap_err().offset, 10);
+        assert_eq!(parse("\"a").unwrap_err().offset, 0);
+        assert_eq!(parse("a b").unwrap_err().offset, 2);
+        assert_eq!(parse("").unwrap_err().offset, 0);
+    }
+}
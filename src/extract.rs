@@ -0,0 +1,215 @@
+use log::warn;
+use crate::error::{Result, T2eError};
+use crate::expr::{self, ParseError};
+use crate::template::{self, Filter, Template};
+use crate::textmate;
+use crate::vscode;
+
+/// Pulls the list of enum entries out of the input text.
+pub trait Extractor {
+    fn extract(&self, text: &str) -> Result<Vec<String>>;
+}
+
+/// One entry per line of text.
+pub struct Lines;
+
+impl Extractor for Lines {
+    fn extract(&self, text: &str) -> Result<Vec<String>> {
+        Ok(text.lines().map(String::from).collect())
+    }
+}
+
+/// One field of every `<template>` in JetBrains live template XML that passes the filter.
+///
+/// Without `strict`, templates that can't be read are reported as warnings and skipped; see
+/// [`template::read`](../template/fn.read.html).
+pub struct TemplateFields {
+    pub strict: bool,
+    pub filter: Filter,
+    /// `name`, `description`, `value`, or a format string combining them such as
+    /// `{name} — {description}`; see [`Template::format`](../template/struct.Template.html#method.format).
+    pub field: String,
+    /// Put `group: ` in front of every entry.
+    pub prefix_group: bool,
+}
+
+impl TemplateFields {
+    /// Like [`extract`](#method.extract), but with the entries of every template set apart.
+    pub fn extract_by_group(&self, xml: &str) -> Result<Vec<(String, Vec<String>)>> {
+        let format = self.format();
+        Ok(template::group_by_set(self.templates(xml)?).into_iter()
+            .map(|(group, templates)| (group, templates.iter().map(|t| t.format(&format)).collect()))
+            .collect())
+    }
+
+    /// The templates that pass the filter, for emitting them whole.
+    ///
+    /// VS Code, Sublime Text and TextMate snippets are converted to templates, with a warning for
+    /// whatever doesn't translate.
+    pub fn templates(&self, xml: &str) -> Result<Vec<Template>> {
+        if vscode::is_snippets(xml) {
+            let mut templates = Vec::new();
+            for snippet in vscode::read(xml)? {
+                let (template, notes) = snippet.to_template();
+                for note in notes {
+                    warn!("Snippet \"{}\": {}", snippet.name, note);
+                }
+                templates.push(template);
+            }
+            return Ok(templates.into_iter().filter(|t| self.filter.matches(t)).collect());
+        }
+        let mut templates = Vec::new();
+        if textmate::is_snippet(xml) {
+            for (format, snippet) in textmate::read(xml)? {
+                let (template, notes) = textmate::to_template(format, &snippet);
+                for note in notes {
+                    warn!("Snippet \"{}\": {}", snippet.name, note);
+                }
+                templates.push(template);
+            }
+        }
+        // Template files may come along with snippet files.
+        if !textmate::is_snippet(xml) || textmate::has_templates(xml) {
+            let (read, skipped) = template::read(xml, self.strict)?;
+            for s in &skipped {
+                warn!("Skipped template at byte {}: {}", s.offset, s.error);
+            }
+            templates.extend(read);
+        }
+        Ok(templates.into_iter().filter(|t| self.filter.matches(t)).collect())
+    }
+
+    fn format(&self) -> String {
+        let format = match self.field.as_str() {
+            "name" | "description" | "value" | "group" => format!("{{{}}}", self.field),
+            // What converted snippets take their name from.
+            "prefix" => "{name}".to_string(),
+            format => format.to_string(),
+        };
+        if self.prefix_group {
+            format!("{{group}}: {}", format)
+        } else {
+            format
+        }
+    }
+}
+
+impl Default for TemplateFields {
+    fn default() -> TemplateFields {
+        TemplateFields { strict: false, filter: Filter::default(), field: "name".to_string(), prefix_group: false }
+    }
+}
+
+impl Extractor for TemplateFields {
+    fn extract(&self, xml: &str) -> Result<Vec<String>> {
+        let format = self.format();
+        Ok(self.templates(xml)?.iter().map(|t| t.format(&format)).collect())
+    }
+}
+
+/// The arguments of a JetBrains `enum("a", "b", ...)` expression, i.e. the reverse of
+/// [`JetBrainsEnum`](../emit/struct.JetBrainsEnum.html).
+pub struct EnumExpression;
+
+impl Extractor for EnumExpression {
+    fn extract(&self, text: &str) -> Result<Vec<String>> {
+        expr::parse(text)?.as_enumeration().ok_or_else(|| T2eError::Expression(ParseError {
+            offset: text.len() - text.trim_start().len(),
+            message: "Not an enum() expression".to_string(),
+        }))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::{Emitter, JetBrainsEnum};
+    use crate::error::Position;
+
+    #[test]
+    fn test1() {
+        let items = vec!["".to_string(), "g\"3".to_string(), r"C:\temp\".to_string(), "a, b)".to_string()];
+        let expression = JetBrainsEnum.emit(&items);
+        assert_eq!(EnumExpression.extract(&format!("  {}\n", expression)).unwrap(), items);
+    }
+
+    #[test]
+    fn test2() {
+        let error = |text: &str| match EnumExpression.extract(text) {
+            Err(T2eError::Expression(e)) => (e.offset, e.message),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(error(" concat(\"a\")"), (1, "Not an enum() expression".to_string()));
+        assert_eq!(error("enum(\"a\") x").0, 10);
+    }
+
+    #[test]
+    fn test3() {
+        let xml = "<templateSet group=\"x\">\n  <template value=\"v\">\n  </template>\n</templateSet>";
+        match (TemplateFields { strict: true, ..Default::default() }).extract(xml) {
+            Err(T2eError::MissingAttribute { position, .. }) => assert_eq!(position, Position { line: 2, column: 3 }),
+            other => panic!("expected a missing attribute, got {:?}", other),
+        }
+        let xml = "<template name=\"a\">\n  <context>\n</template>";
+        match (TemplateFields { strict: true, ..Default::default() }).extract(xml) {
+            Err(e @ T2eError::Xml { .. }) => {
+                assert_eq!(e.exit_code(), 6);
+                assert!(e.to_string().starts_with("XML error at line 3, column 3 in template \"a\""), "{}", e);
+            }
+            other => panic!("expected an XML error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test4() {
+        let xml = r#"me="cut" value="x">
+  </template>
+<template name="a" value="1">
+  <context>
+</template>
+<template value="no name"/>
+<template name="b"/>
+<template name="c" value="trunc"#;
+        let (templates, skipped) = template::read(xml, false).unwrap();
+        let names: Vec<String> = templates.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].offset, 88);
+        assert_eq!(skipped[0].error.exit_code(), 7);
+        assert_eq!(skipped[1].offset, 137);
+        assert_eq!(skipped[1].error.exit_code(), 6);
+        assert_eq!(TemplateFields::default().extract(xml).unwrap(), names);
+        assert!((TemplateFields { strict: true, ..Default::default() }).extract(xml).is_err());
+    }
+
+    #[test]
+    fn test5() {
+        let xml = r#"<template name="ll" value="ls -l&#10;" description="List files" />
+<template name="pwd" value="pwd" description="" />"#;
+        let fields = |field: &str| TemplateFields { field: field.to_string(), ..Default::default() }.extract(xml).unwrap();
+        assert_eq!(fields("description"), vec!["List files".to_string(), "".to_string()]);
+        assert_eq!(fields("value"), vec!["ls -l\n".to_string(), "pwd".to_string()]);
+        assert_eq!(fields("{name} — {description}"), vec!["ll — List files".to_string(), "pwd — ".to_string()]);
+    }
+
+    #[test]
+    fn test6() {
+        let text = r#"{
+  "For": { "prefix": "for", "scope": "python", "body": ["for $1 in $2:"], "description": "Loop" },
+  "Main": { "prefix": "main", "scope": "java", "body": ["main"] }
+}"#;
+        let extractor = |field: &str| TemplateFields { field: field.to_string(), ..Default::default() };
+        let fields = |field: &str| extractor(field).extract(text).unwrap();
+        assert_eq!(fields("name"), vec!["for", "main"]);
+        assert_eq!(fields("prefix"), vec!["for", "main"]);
+        assert_eq!(fields("value"), vec!["for $VAR1$ in $VAR2$:", "main"]);
+        assert_eq!(fields("{name} - {description}"), vec!["for - Loop", "main - Main"]);
+        assert_eq!(fields("bogus"), vec!["bogus", "bogus"]);
+        let grouped = TemplateFields { prefix_group: true, ..extractor("name") };
+        assert_eq!(grouped.extract(text).unwrap(), vec!["VS Code: for", "VS Code: main"]);
+        assert_eq!(extractor("name").extract_by_group(text).unwrap(), vec![("VS Code".to_string(), vec!["for".to_string(), "main".to_string()])]);
+        let filtered = TemplateFields { filter: Filter { contexts: vec!["Python".to_string()], ..Default::default() }, ..extractor("name") };
+        assert_eq!(filtered.extract(text).unwrap(), vec!["for"]);
+    }
+}
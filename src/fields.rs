@@ -0,0 +1,252 @@
+//! A template value as numbered fields, the model that snippet formats of other editors share.
+//!
+//! Variables become fields numbered in the IDE's order: declared variables first, then undeclared
+//! ones. An `enum()` becomes a choice and a string default value the field's initial text.
+
+use log::warn;
+use crate::expr::{self, Expr};
+use crate::template::{self, Template, END, PREDEFINED, SELECTION};
+
+/// A piece of a template value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    /// Literal text, with `$$` already turned into `$`.
+    Text(String),
+    /// The first use of a variable: a field to fill in.
+    Field(Field),
+    /// A later use of the variable with this field number, repeating what was typed there.
+    Mirror(usize),
+    /// A variable the editor computes itself each time it's used.
+    Computed(Expr),
+    End,
+    Selection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub number: usize,
+    pub default: Option<String>,
+    /// The options of an `enum()`; empty for a free-form field.
+    pub choices: Vec<String>,
+}
+
+/// Splits the value of `template` into pieces, along with notes on whatever can't be carried over.
+///
+/// `computable` tells whether the editor can compute an expression itself; such variables don't
+/// get a field number.
+pub fn pieces(template: &Template, computable: impl Fn(&Expr) -> bool) -> (Vec<Piece>, Vec<String>) {
+    let mut notes = Vec::new();
+    let used = template.used_variables();
+    let mut order: Vec<&str> = template.variables.iter().map(|v| v.name.as_str()).filter(|n| used.iter().any(|u| u == n)).collect();
+    for name in &used {
+        if !order.contains(&name.as_str()) && !PREDEFINED.contains(&name.as_str()) {
+            order.push(name);
+        }
+    }
+
+    // The piece for the first use of each variable.
+    let mut firsts: Vec<(&str, Piece)> = Vec::new();
+    let mut number = 0;
+    for name in order {
+        let variable = template.variables.iter().find(|v| v.name == name);
+        let expression = variable.map(|v| v.expression.trim()).unwrap_or_default();
+        let parsed = expr::parse(expression).ok();
+        if let Some(e) = parsed.as_ref().filter(|e| e.as_enumeration().is_none() && computable(e)) {
+            firsts.push((name, Piece::Computed(e.clone())));
+            continue;
+        }
+        number += 1;
+        let choices = parsed.as_ref().and_then(Expr::as_enumeration).unwrap_or_default();
+        if choices.is_empty() && !expression.is_empty() {
+            notes.push(format!("Expression {} of {} can't be translated", expression, name));
+        }
+        let default = if !choices.is_empty() {
+            None
+        } else {
+            let default = variable.map(|v| v.default_value.trim()).unwrap_or_default();
+            match expr::parse(default) {
+                Ok(Expr::Str(text)) => Some(text),
+                _ if default.is_empty() => None,
+                _ => {
+                    notes.push(format!("Default value {} of {} can't be translated", default, name));
+                    None
+                }
+            }
+        };
+        firsts.push((name, Piece::Field(Field { number, default, choices })));
+    }
+
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut seen: Vec<&str> = Vec::new();
+    let mut rest = template.value.as_str();
+    while let Some(i) = rest.find('$') {
+        text.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            text.push('$');
+            rest = after;
+            continue;
+        }
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len == 0 || !rest[len..].starts_with('$') {
+            text.push('$');
+            continue;
+        }
+        let name = &rest[..len];
+        rest = &rest[len + 1..];
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(match name {
+            END => Piece::End,
+            SELECTION => Piece::Selection,
+            name => {
+                let (_, first) = firsts.iter().find(|(n, _)| *n == name).unwrap();
+                match first {
+                    Piece::Field(field) if seen.contains(&name) => Piece::Mirror(field.number),
+                    first => first.clone(),
+                }
+            }
+        });
+        seen.push(name);
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    (pieces, notes)
+}
+
+/// How a snippet format writes each kind of piece. Fields without initial text, mirrors and the
+/// end are `${1}`, `$1` and `$0` in all of them.
+pub trait Syntax {
+    /// Literal text, escaped.
+    fn text(&self, text: &str) -> String;
+    /// A field starting out as `default`.
+    fn placeholder(&self, number: usize, default: &str) -> String;
+    /// A field offering `choices`, noting it in `notes` if the format can't quite.
+    fn choice(&self, number: usize, choices: &[String], notes: &mut Vec<String>) -> String;
+    /// What the editor computes `expression` with, if it can.
+    fn computed(&self, expression: &Expr) -> Option<String>;
+    /// The text selected when the snippet was inserted.
+    fn selection(&self) -> String;
+}
+
+/// The value of `template` written in `syntax`, along with notes on whatever can't be carried over.
+pub fn render(template: &Template, syntax: &impl Syntax) -> (String, Vec<String>) {
+    let (pieces, mut notes) = pieces(template, |e| syntax.computed(e).is_some());
+    let mut body = String::new();
+    for piece in pieces {
+        body.push_str(&match piece {
+            Piece::Text(text) => syntax.text(&text),
+            Piece::Field(field) if !field.choices.is_empty() => syntax.choice(field.number, &field.choices, &mut notes),
+            Piece::Field(Field { number, default: Some(default), .. }) => syntax.placeholder(number, &default),
+            Piece::Field(field) => format!("${{{}}}", field.number),
+            Piece::Mirror(number) => format!("${}", number),
+            Piece::Computed(e) => syntax.computed(&e).unwrap(),
+            Piece::End => "$0".to_string(),
+            Piece::Selection => syntax.selection(),
+        });
+    }
+    (body, notes)
+}
+
+/// Looks up `expression` in a table of template expressions and what an editor writes for them.
+pub fn lookup(table: &[(&str, &'static str)], expression: &Expr) -> Option<&'static str> {
+    table.iter().find(|(e, _)| expr::parse(e).ok().as_ref() == Some(expression)).map(|(_, to)| *to)
+}
+
+/// Templates by the language they are enabled in, as named in `languages` by an editor that keeps
+/// snippets per language, sorted by that name. A template goes under every language of its
+/// enabled contexts, or under `fallback` when none of them is known.
+pub fn by_language<'a>(templates: &'a [Template], languages: &[(&str, &'static str)], fallback: &'static str) -> Languages<'a> {
+    let mut found: Languages = Vec::new();
+    for t in templates {
+        let mut names = Vec::new();
+        for context in t.contexts.iter().filter(|c| c.value) {
+            match languages.iter().find(|(c, _)| *c == template::language_context(&context.name)) {
+                Some((_, name)) if !names.contains(name) => names.push(*name),
+                Some(_) => (),
+                None => warn!("Template \"{}\": no snippet file for context {}", t.name, context.name),
+            }
+        }
+        if names.is_empty() {
+            names.push(fallback);
+        }
+        for name in names {
+            match found.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, list)) => list.push(t),
+                None => found.push((name, vec![t])),
+            }
+        }
+    }
+    found.sort_by_key(|(name, _)| *name);
+    found
+}
+
+/// Puts a backslash before every `special` character.
+pub fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A file name for each template's snippet, after its name; repeated names get `-2`, `-3`, ...
+pub fn file_names(templates: &[&Template]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for template in templates {
+        let base: String = template.name.chars().map(|c| if c.is_alphanumeric() || "-_.".contains(c) { c } else { '_' }).collect();
+        let base = if base.is_empty() || base.starts_with('.') { format!("_{}", base) } else { base };
+        let mut name = base.clone();
+        let mut n = 1;
+        while names.contains(&name) {
+            n += 1;
+            name = format!("{}-{}", base, n);
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// Templates by language.
+pub type Languages<'a> = Vec<(&'static str, Vec<&'a Template>)>;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        let xml = r#"<templateSet group="g">
+  <template name="t" value="$$ $A$=$B$ $F$ $A$ $C$$END$ $" description="">
+    <variable name="B" expression="enum(&quot;x&quot;, &quot;y&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="A" expression="" defaultValue="&quot;a&quot;" alwaysStopAt="true" />
+    <variable name="F" expression="fileName()" defaultValue="" alwaysStopAt="false" />
+  </template>
+</templateSet>"#;
+        let (templates, _) = template::read(xml, true).unwrap();
+        let (pieces, notes) = pieces(&templates[0], |e| lookup(&[("fileName()", "f")], e).is_some());
+        assert_eq!(pieces, vec![
+            Piece::Text("$ ".to_string()),
+            Piece::Field(Field { number: 2, default: Some("a".to_string()), choices: vec![] }),
+            Piece::Text("=".to_string()),
+            Piece::Field(Field { number: 1, default: None, choices: vec!["x".to_string(), "y".to_string()] }),
+            Piece::Text(" ".to_string()),
+            Piece::Computed(expr::parse("fileName()").unwrap()),
+            Piece::Text(" ".to_string()),
+            Piece::Mirror(2),
+            Piece::Text(" ".to_string()),
+            Piece::Field(Field { number: 3, default: None, choices: vec![] }),
+            Piece::End,
+            Piece::Text(" $".to_string()),
+        ]);
+        assert!(notes.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Checks for mistakes in live templates that the IDE accepts silently.
+
+use serde_json::{json, Value};
+use std::fmt;
+use crate::expr;
+use crate::template::{self, Template, PREDEFINED};
+
+/// Contexts in which `toReformat` does nothing because no formatter applies: the IntelliJ
+/// platform's `OTHER` ("Everywhere") and `TEXT` (plain text) template context types. Languages
+/// whose plugin may or may not bring a formatter, such as Markdown, are left out so that the lint
+/// never fails a build wrongly.
+const NOT_REFORMATTABLE: &[&str] = &["OTHER", "TEXT"];
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// Index of the template in the linted slice.
+    pub template: usize,
+    /// A short name for the check, e.g. `undeclared-variable`.
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn to_json(&self, template: &Template) -> Value {
+        json!({
+            "rule": self.rule,
+            "severity": self.severity.to_string(),
+            "group": template.group,
+            "name": template.name,
+            "message": self.message,
+        })
+    }
+}
+
+/// Runs every check over `templates`, which may come from several files, in template order.
+///
+/// | rule                    | severity | finding                                                |
+/// |-------------------------|----------|--------------------------------------------------------|
+/// | undeclared-variable     | error    | `$X$` in the value without a `<variable>`              |
+/// | unused-variable         | warning  | a `<variable>` the value doesn't use                   |
+/// | broken-enum             | error    | an `enum()` expression that doesn't parse              |
+/// | invalid-expression      | error    | any other expression that doesn't parse                |
+/// | duplicate-abbreviation  | warning  | a name already used by an earlier template             |
+/// | empty-context           | error    | no context enabled, so the template is never offered   |
+/// | missing-end             | warning  | no `$END$`; the caret ends up after the text           |
+/// | repeated-end            | error    | more than one `$END$`                                  |
+/// | useless-reformat        | warning  | `toReformat` where no enabled language has a formatter |
+pub fn lint(templates: &[Template]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (i, template) in templates.iter().enumerate() {
+        let mut report = |rule, severity, message: String| findings.push(Finding { template: i, rule, severity, message });
+        let used = template.used_variables();
+
+        let mut undeclared: Vec<&String> = Vec::new();
+        for name in &used {
+            if !PREDEFINED.contains(&name.as_str()) && !template.variables.iter().any(|v| v.name == *name) && !undeclared.contains(&name) {
+                undeclared.push(name);
+            }
+        }
+        for name in undeclared {
+            report("undeclared-variable", Severity::Error, format!("${}$ is used but not declared", name));
+        }
+        for variable in &template.variables {
+            if !used.contains(&variable.name) {
+                report("unused-variable", Severity::Warning, format!("Variable {} is declared but not used", variable.name));
+            }
+            if variable.expression.trim().is_empty() {
+                continue;
+            }
+            if let Err(e) = expr::parse(&variable.expression) {
+                let rule = if variable.expression.trim_start().starts_with("enum") { "broken-enum" } else { "invalid-expression" };
+                report(rule, Severity::Error, format!("Expression of {}: {}", variable.name, e));
+            }
+        }
+
+        if let Some(earlier) = templates[..i].iter().find(|t| t.name == template.name && t.shares_context_with(template)) {
+            report("duplicate-abbreviation", Severity::Warning,
+                format!("\"{}\" in group \"{}\" is also defined in group \"{}\" for the same context", template.name, template.group, earlier.group));
+        }
+        if !template.contexts.iter().any(|c| c.value) {
+            report("empty-context", Severity::Error, "No context is enabled".to_string());
+        }
+        match used.iter().filter(|name| *name == template::END).count() {
+            0 => report("missing-end", Severity::Warning, "No $END$".to_string()),
+            1 => (),
+            n => report("repeated-end", Severity::Error, format!("$END$ appears {} times", n)),
+        }
+        let enabled: Vec<&str> = template.contexts.iter().filter(|c| c.value).map(|c| c.name.as_str()).collect();
+        if template.to_reformat && !enabled.is_empty() && enabled.iter().all(|c| NOT_REFORMATTABLE.contains(c)) {
+            report("useless-reformat", Severity::Warning, format!("toReformat has no effect in {}", enabled.join(", ")));
+        }
+    }
+    findings
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+
+    #[test]
+    fn test1() {
+        let xml = r#"<templateSet group="a">
+  <template name="good" value="echo $X$$END$" toReformat="false">
+    <variable name="X" expression="enum(&quot;a\\&quot;, &quot;b&quot;)" defaultValue="" alwaysStopAt="true" />
+    <context><option name="SHELL_SCRIPT" value="true" /></context>
+  </template>
+  <template name="bad" value="$Y$ $END$ $END$ $Y$" toReformat="true">
+    <variable name="Z" expression="capitalize(" defaultValue="" alwaysStopAt="true" />
+    <context><option name="OTHER" value="true" /></context>
+  </template>
+</templateSet>
+<templateSet group="b">
+  <template name="good" value="" toReformat="false">
+    <context><option name="OTHER" value="false" /></context>
+  </template>
+</templateSet>
+<templateSet group="c">
+  <template name="good" value="$END$" toReformat="false">
+    <context><option name="JAVA_CODE" value="true" /><option name="SHELL_SCRIPT" value="true" /></context>
+  </template>
+  <template name="bad" value="$END$" toReformat="false">
+    <context><option name="JAVA_CODE" value="true" /></context>
+  </template>
+</templateSet>"#;
+        let (templates, _) = template::read(xml, true).unwrap();
+        let findings = lint(&templates);
+        let rules: Vec<(usize, &str)> = findings.iter().map(|f| (f.template, f.rule)).collect();
+        assert_eq!(rules, vec![
+            (1, "undeclared-variable"),
+            (1, "unused-variable"),
+            (1, "invalid-expression"),
+            (1, "repeated-end"),
+            (1, "useless-reformat"),
+            (2, "empty-context"),
+            (2, "missing-end"),
+            (3, "duplicate-abbreviation"),
+        ]);
+        assert_eq!(findings[0].message, "$Y$ is used but not declared");
+        assert_eq!(findings[7].to_json(&templates[3])["message"], "\"good\" in group \"c\" is also defined in group \"a\" for the same context");
+
+        let broken = Template {
+            value: "$X$$END$".to_string(),
+            variables: vec![template::Variable { name: "X".to_string(), expression: r#"enum("a\")"#.to_string(), ..Default::default() }],
+            contexts: vec![template::ContextOption { name: "OTHER".to_string(), value: true }],
+            ..Default::default()
+        };
+        assert_eq!(lint(&[broken]).iter().map(|f| f.rule).collect::<Vec<_>>(), vec!["broken-enum"]);
+
+        let markdown = Template {
+            value: "$END$".to_string(),
+            to_reformat: true,
+            contexts: vec![template::ContextOption { name: "Markdown".to_string(), value: true }],
+            ..Default::default()
+        };
+        assert!(lint(&[markdown]).is_empty());
+    }
+}
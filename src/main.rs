@@ -1,9 +1,14 @@
-use clipboard::{ClipboardContext, ClipboardProvider};
-use quick_xml::Reader;
-use quick_xml::events::Event;
 use clap::{*};
 use std::env;
-use log::{trace, debug, info, warn, error};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use log::{trace, info, warn, error};
+use t2e::{cheatsheet, diff, discover, emit, eval, expr, extract, lint, merge, sink, snippet, source, template, textmate, ultisnips, vscode, yasnippet};
+use t2e::{Emitter, Extractor, Source, T2eError};
+use t2e::emit::{FilesEmitter, TemplateEmitter};
+use t2e::error::Position;
+use t2e::template::Filter;
 
 
 fn main() {
@@ -17,11 +22,239 @@ fn main() {
             .value_name("FROM-LINES")
             .help("Generate enum from lines of text in the clipboard")
             .takes_value(false))
+        .arg(Arg::with_name("reverse")
+            .short("r")
+            .long("reverse")
+            .help("Parse an enum() expression and write one value per line")
+            .conflicts_with("from-lines")
+            .takes_value(false))
+        .arg(Arg::with_name("from-snippet")
+            .short("s")
+            .long("from-snippet")
+            .help("Make a template from a code snippet with placeholders such as ${name}, <<name>> or $NAME$; \
+                   ${name=a|b|c} offers a choice of values. Requires --template")
+            .conflicts_with_all(&["from-lines", "reverse"])
+            .requires("template")
+            .takes_value(false))
+        .arg(Arg::with_name("strict")
+            .long("strict")
+            .help("Stop at the first malformed template instead of skipping it; VS Code snippets files are always read this way")
+            .takes_value(false))
+        .arg(Arg::with_name("field")
+            .short("f")
+            .long("field")
+            .value_name("FIELD|FORMAT")
+            .help("What each template contributes: name (default), description, value, or a format such as '{name} - {description}'; \
+                   VS Code snippets are converted first, so their name is their prefix")
+            .takes_value(true))
+        .arg(Arg::with_name("context")
+            .short("c")
+            .long("context")
+            .value_name("CONTEXTS")
+            .help("Only use templates enabled in one of these contexts, e.g. SHELL_SCRIPT,CSS")
+            .takes_value(true)
+            .use_delimiter(true)
+            .multiple(true))
+        .arg(Arg::with_name("exclude-context")
+            .short("x")
+            .long("exclude-context")
+            .value_name("CONTEXTS")
+            .help("Leave out templates enabled in any of these contexts")
+            .takes_value(true)
+            .use_delimiter(true)
+            .multiple(true))
+        .arg(Arg::with_name("group")
+            .short("g")
+            .long("group")
+            .value_name("GROUPS")
+            .help("Only use templates from these templateSet groups")
+            .takes_value(true)
+            .use_delimiter(true)
+            .multiple(true))
+        .arg(Arg::with_name("prefix-group")
+            .long("prefix-group")
+            .help("Prefix every entry with its templateSet group, e.g. 'shell: name'")
+            .takes_value(false))
+        .arg(Arg::with_name("per-group")
+            .long("per-group")
+            .help("Generate one enum per templateSet group, each under the group name")
+            .takes_value(false))
+        .arg(Arg::with_name("template")
+            .short("t")
+            .long("template")
+            .value_name("NAME")
+            .help("Wrap the enum in a complete <template> element with this abbreviation, ready to paste into a templates file")
+            .conflicts_with("reverse")
+            .takes_value(true))
+        .arg(Arg::with_name("vscode")
+            .long("vscode")
+            .help("Generate a VS Code choice placeholder, ${1|a,b,c|}, instead of an enum; with --template, a .code-snippets file")
+            .conflicts_with_all(&["reverse", "from-snippet"])
+            .takes_value(false))
+        .arg(Arg::with_name("description")
+            .long("description")
+            .value_name("TEXT")
+            .help("Description of the generated template")
+            .requires("template")
+            .takes_value(true))
+        .arg(Arg::with_name("variable")
+            .long("variable")
+            .value_name("NAME")
+            .help("Name of the generated template's variable [default: X]")
+            .requires("template")
+            .takes_value(true))
+        .arg(Arg::with_name("template-context")
+            .long("template-context")
+            .value_name("CONTEXTS")
+            .help("Contexts the generated template is enabled in [default: OTHER]")
+            .requires("template")
+            .takes_value(true)
+            .use_delimiter(true)
+            .multiple(true))
+        .arg(Arg::with_name("export")
+            .short("e")
+            .long("export")
+            .value_name("FORMAT")
+            .help("Write the templates themselves instead of an enum: JetBrains XML or VS Code snippets, \
+                   converting between the two, a markdown or html cheat sheet, or UltiSnips, yasnippet, \
+                   Sublime Text or TextMate snippets into the --output directory")
+            .possible_values(&["jetbrains", "vscode", "markdown", "html", "ultisnips", "yasnippet", "sublime", "textmate"])
+            .conflicts_with_all(&["from-lines", "reverse", "from-snippet", "template", "per-group"])
+            .takes_value(true))
+        .arg(Arg::with_name("input")
+            .short("i")
+            .long("input")
+            .value_name("PATH|-")
+            .help("Read from the given file, glob or '-' (stdin) instead of the clipboard; .zip settings exports and .jar plugins are searched for templates; can be repeated")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("ide")
+            .long("ide")
+            .value_name("NAME")
+            .help("Read the live templates of an installed IDE, e.g. PyCharm (newest version) or PyCharm2020.1; see 'ides'")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .value_name("clipboard|stdout|PATH")
+            .help("Where to write the generated enum: the clipboard (default), stdout or a file; \
+                   a directory for --export ultisnips, yasnippet, sublime or textmate")
+            .takes_value(true))
+        .arg(Arg::with_name("append")
+            .short("a")
+            .long("append")
+            .help("Append to the output file instead of overwriting it")
+            .requires("output")
+            .takes_value(false))
         .arg(Arg::with_name("debug")
             .long("debug")
             .help("Show debugging info")
             .takes_value(false)
             .hidden(true))
+        .subcommand(SubCommand::with_name("expr")
+            .about("Validate, pretty-print and preview a live template variable expression")
+            .arg(Arg::with_name("EXPRESSION")
+                .help("The expression, e.g. 'capitalize(concat(NAME, \"Impl\"))'")
+                .required(true))
+            .arg(Arg::with_name("var")
+                .long("var")
+                .value_name("NAME=VALUE")
+                .help("Value of another template variable used by the expression; can be repeated")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)))
+        .subcommand(SubCommand::with_name("merge")
+            .about("Merge template files into one template set per group, sorted by name, dropping duplicates; \
+                several groups go to one GROUP.xml file each in the output directory")
+            .arg(Arg::with_name("FILES")
+                .help("Template files; for duplicates, the first is 'ours' and the last 'theirs'")
+                .required(true)
+                .multiple(true))
+            .arg(Arg::with_name("prefer")
+                .long("prefer")
+                .value_name("ours|theirs|newest")
+                .help("Which template to keep when duplicates have different values; newest goes by file modification time")
+                .possible_values(&["ours", "theirs", "newest"])
+                .default_value("ours"))
+            .arg(Arg::with_name("group")
+                .long("group")
+                .value_name("NAME")
+                .help("Merge the templates of all groups into a single template set of this group")
+                .takes_value(true))
+            .arg(Arg::with_name("strict")
+                .long("strict")
+                .help("Stop at the first malformed template instead of skipping it"))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("clipboard|stdout|PATH")
+                .help("Where to write the merged templates; a directory when there are several groups")
+                .default_value("stdout")))
+        .subcommand(SubCommand::with_name("diff")
+            .about("Show which templates were added, removed, renamed or changed between two template files")
+            .arg(Arg::with_name("OLD")
+                .required(true))
+            .arg(Arg::with_name("NEW")
+                .required(true))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print the differences as JSON"))
+            .arg(Arg::with_name("strict")
+                .long("strict")
+                .help("Stop at the first malformed template instead of skipping it")))
+        .subcommand(SubCommand::with_name("lint")
+            .about("Check template files for undeclared or unused variables, broken expressions, duplicates and other mistakes")
+            .arg(Arg::with_name("FILES")
+                .required(true)
+                .multiple(true))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Print the problems as a JSON array"))
+            .arg(Arg::with_name("fail-on")
+                .long("fail-on")
+                .value_name("warning|error")
+                .help("Exit with code 9 if a problem this severe or worse is found")
+                .possible_values(&["warning", "error"])
+                .default_value("error"))
+            .arg(Arg::with_name("strict")
+                .long("strict")
+                .help("Stop at the first malformed template instead of skipping it")))
+        .subcommand(SubCommand::with_name("fmt")
+            .about("Rewrite template files in canonical form: sorted templates and context options, fixed attribute order")
+            .arg(Arg::with_name("FILES")
+                .help("Files to rewrite in place; without any, formats stdin to stdout")
+                .multiple(true))
+            .arg(Arg::with_name("check")
+                .long("check")
+                .help("Don't write anything; exit with code 10 if a file isn't in canonical form")))
+        .subcommand(SubCommand::with_name("convert")
+            .about("Convert JetBrains template files to VS Code snippets or back, reporting what can't be translated")
+            .arg(Arg::with_name("FILES")
+                .help("Template XML, VS Code snippet, .sublime-snippet or .tmSnippet files")
+                .required(true)
+                .multiple(true))
+            .arg(Arg::with_name("to")
+                .long("to")
+                .value_name("vscode|jetbrains")
+                .help("Target format [default: the other one than the first file's]")
+                .possible_values(&["vscode", "jetbrains"]))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("clipboard|stdout|PATH")
+                .help("Where to write the converted templates")
+                .default_value("stdout"))
+            .arg(Arg::with_name("report")
+                .long("report")
+                .value_name("clipboard|stdout|PATH")
+                .help("Also write the untranslatable constructs there, one per line")))
+        .subcommand(SubCommand::with_name("ides")
+            .about("List the JetBrains IDE configurations found and their live template files")
+            .after_help("Searched: $JETBRAINS_CONFIG if set, otherwise ~/.config/JetBrains/*, \
+                ~/.<Product><Version>/config and Toolbox App installations."))
         .get_matches();
 
     if matches.occurrences_of("debug") == 1 {
@@ -39,168 +272,399 @@ fn main() {
         }
     }
 
-    let mut clipboard: ClipboardContext = ClipboardProvider::new().unwrap();
-    let text = clipboard.get_contents().unwrap();
+    if let Err(e) = run(&matches) {
+        error!("{}", e);
+        process::exit(e.exit_code());
+    }
+    env::remove_var("T2E_RUST_APP_LOG");
+
+    if matches.occurrences_of("debug") == 1 {
+        trace!("Environment variable T2E_RUST_APP_LOG removed!");
+    }
+}
+
+fn run(matches: &ArgMatches) -> t2e::Result<()> {
+    if let Some(matches) = matches.subcommand_matches("expr") {
+        return run_expr(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("merge") {
+        return run_merge(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        return run_diff(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        return run_lint(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("fmt") {
+        return run_fmt(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("convert") {
+        return run_convert(matches);
+    }
+    if matches.subcommand_matches("ides").is_some() {
+        return run_ides();
+    }
+
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    for input in matches.values_of("input").into_iter().flatten() {
+        sources.extend(source::from_arg(input)?);
+    }
+    for ide in matches.values_of("ide").into_iter().flatten() {
+        sources.extend(source::from_ide(ide)?);
+    }
+    if sources.is_empty() {
+        sources.push(Box::new(source::ClipboardSource));
+    }
+
+    let text = source::read_all(&mut sources)?;
 
-    trace!("Data in clipboard: ");
+    trace!("Input data: ");
     info!("{}", text);
 
-    let mut result = "".to_string();
+    let template = matches.value_of("template").map(|name| {
+        let mut template = emit::TemplateXml {
+            name: name.to_string(),
+            description: matches.value_of("description").unwrap_or_default().to_string(),
+            ..Default::default()
+        };
+        if let Some(variable) = matches.value_of("variable") {
+            template.variable = variable.to_string();
+        }
+        if matches.is_present("template-context") {
+            template.contexts = values(matches, "template-context");
+        }
+        template
+    });
 
-    if matches.occurrences_of("from-lines") == 1 {
+    let vscode = matches.is_present("vscode");
+    let result = if let (true, Some(template)) = (matches.is_present("from-snippet"), &template) {
+        let (value, variables) = snippet::parse(&text);
+        template.template(value, variables).to_xml()
+    } else if matches.occurrences_of("from-lines") == 1 {
         trace!("-from-lines: provided");
-        result = get_enum_from_lines(&text);
+        emitter(template, vscode).emit(&extract::Lines.extract(&text)?)
+    } else if matches.is_present("reverse") {
+        trace!("-reverse: provided");
+        emit::PlainLines.emit(&extract::EnumExpression.extract(&text)?)
     } else {
-        result = get_enum_from_templates(&text);
-    }
+        let filter = Filter {
+            contexts: values(matches, "context"),
+            exclude_contexts: values(matches, "exclude-context"),
+            groups: values(matches, "group"),
+        };
+        let extractor = extract::TemplateFields {
+            strict: matches.is_present("strict"),
+            filter,
+            field: matches.value_of("field").unwrap_or("name").to_string(),
+            prefix_group: matches.is_present("prefix-group"),
+        };
+        if let Some(emitter) = matches.value_of("export").and_then(files_emitter) {
+            let files = emitter.emit_files(&extractor.templates(&text)?);
+            match matches.value_of("output") {
+                Some(dir) if !["clipboard", "stdout", "-"].contains(&dir) => return sink::write_files(dir.as_ref(), &files),
+                _ => files.iter()
+                    .map(|(path, content)| format!("==> {} <==\n{}", path.display(), content.trim_end()))
+                    .collect::<Vec<String>>()
+                    .join("\n\n"),
+            }
+        } else if let Some(format) = matches.value_of("export") {
+            template_emitter(format).emit_templates(&extractor.templates(&text)?)
+        } else if matches.is_present("per-group") {
+            let emitter = emitter(template, vscode);
+            let groups = extractor.extract_by_group(&text)?;
+            let blocks: Vec<String> = groups.iter()
+                .map(|(group, entries)| format!("{}\n{}", group, emitter.emit(entries)))
+                .collect();
+            blocks.join("\n\n")
+        } else {
+            emitter(template, vscode).emit(&extractor.extract(&text)?)
+        }
+    };
 
-    trace!("Generated result (in clipboard): ");
+    let output = matches.value_of("output").unwrap_or("clipboard");
+    trace!("Generated result (in {}): ", output);
     info!("{}", result);
 
-    clipboard.set_contents(result.to_owned()).unwrap();
-    env::remove_var("T2E_RUST_APP_LOG");
+    sink::from_arg(output, matches.is_present("append")).write(&result)
+}
 
-    if matches.occurrences_of("debug") == 1 {
-        trace!("Environment variable T2E_RUST_APP_LOG removed!");
+/// The `enum()` or VS Code choice alone, or wrapped in a template when --template is given.
+fn emitter(template: Option<emit::TemplateXml>, vscode: bool) -> Box<dyn Emitter> {
+    match (template, vscode) {
+        (Some(template), true) => Box::new(vscode::Snippet {
+            prefix: template.name.clone(),
+            name: template.name,
+            description: template.description,
+            choice: vscode::Choice::default(),
+        }),
+        (Some(template), false) => Box::new(template),
+        (None, true) => Box::new(vscode::Choice::default()),
+        (None, false) => Box::new(emit::JetBrainsEnum),
+    }
+}
+
+/// The exporter for an --export format.
+fn template_emitter(format: &str) -> Box<dyn TemplateEmitter> {
+    match format {
+        "jetbrains" => Box::new(emit::TemplateSets),
+        "vscode" => Box::new(vscode::Snippets),
+        "html" => Box::new(cheatsheet::Html),
+        _ => Box::new(cheatsheet::Markdown),
+    }
+}
+
+/// The exporter for an --export format that writes a directory tree, if it is one.
+fn files_emitter(format: &str) -> Option<Box<dyn FilesEmitter>> {
+    match format {
+        "ultisnips" => Some(Box::new(ultisnips::UltiSnips)),
+        "yasnippet" => Some(Box::new(yasnippet::Yasnippet)),
+        "sublime" => Some(Box::new(textmate::Snippets { format: textmate::Format::Sublime })),
+        "textmate" => Some(Box::new(textmate::Snippets { format: textmate::Format::TextMate })),
+        _ => None,
+    }
+}
+
+fn values(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches.values_of(name).into_iter().flatten().map(String::from).collect()
+}
+
+/// Writes the merged template sets and warns about every conflict.
+fn run_merge(matches: &ArgMatches) -> t2e::Result<()> {
+    let mut inputs = Vec::new();
+    for file in matches.values_of("FILES").into_iter().flatten() {
+        let mut templates = read_templates(file, matches.is_present("strict"))?;
+        if let Some(group) = matches.value_of("group") {
+            for template in &mut templates {
+                template.group = group.to_string();
+            }
+        }
+        let modified = fs::metadata(file).and_then(|m| m.modified()).ok();
+        inputs.push(merge::Input { label: file.to_string(), templates, modified });
+    }
+
+    let prefer = matches.value_of("prefer").unwrap().parse().map_err(T2eError::Usage)?;
+    let (sets, conflicts) = merge::merge(&inputs, prefer);
+    for conflict in &conflicts {
+        warn!("Conflict: {}", conflict);
+    }
+    let output = matches.value_of("output").unwrap();
+    if sets.len() <= 1 {
+        let result: String = sets.iter().map(|(group, templates)| template::set_to_xml(group, templates)).collect();
+        return sink::from_arg(output, false).write(result.trim_end());
     }
+    // A file holds a single template set, so several groups need a file each.
+    if ["clipboard", "stdout", "-"].contains(&output) {
+        let groups: Vec<&str> = sets.iter().map(|(group, _)| group.as_str()).collect();
+        return Err(T2eError::Usage(format!(
+            "The templates are in several groups ({}); write them to a directory with -o DIR, or into one group with --group NAME",
+            groups.join(", "))));
+    }
+    let files: Vec<(PathBuf, String)> = sets.iter()
+        .map(|(group, templates)| (PathBuf::from(format!("{}.xml", group_file_name(group))), template::set_to_xml(group, templates)))
+        .collect();
+    sink::write_files(output.as_ref(), &files)
 }
 
-fn get_enum_from_lines(text: &String) -> String {
-    format!("enum(\"{}\")", text.lines().map(|line| line.replace("\"", "\\\"")).collect::<Vec<String>>().join("\", \""))
+/// The file name the IDE would use for a template set of `group`, without extension.
+fn group_file_name(group: &str) -> String {
+    let name: String = group.chars().map(|c| if c.is_alphanumeric() || "-_. ".contains(c) { c } else { '_' }).collect();
+    if name.is_empty() || name.starts_with('.') { format!("_{}", name) } else { name }
+}
+
+/// Prints the differences between two template files, as text or JSON.
+fn run_diff(matches: &ArgMatches) -> t2e::Result<()> {
+    let strict = matches.is_present("strict");
+    let old = read_templates(matches.value_of("OLD").unwrap(), strict)?;
+    let new = read_templates(matches.value_of("NEW").unwrap(), strict)?;
+    let diff = diff::diff(&old, &new);
+    if matches.is_present("json") {
+        println!("{:#}", diff.to_json());
+    } else {
+        print!("{}", diff);
+    }
+    Ok(())
+}
+
+/// Prints every problem found in the files, one per line as `file:line:column: ...` or as JSON.
+fn run_lint(matches: &ArgMatches) -> t2e::Result<()> {
+    let mut templates = Vec::new();
+    let mut texts = Vec::new();
+    // The file name and text each template was read from, by template index.
+    let mut origins = Vec::new();
+    for file in matches.values_of("FILES").into_iter().flatten() {
+        let (text, found) = read_templates_with_text(file, matches.is_present("strict"))?;
+        texts.push(text);
+        origins.extend(found.iter().map(|_| (file, texts.len() - 1)));
+        templates.extend(found);
+    }
+    let origin = |i: usize| (origins[i].0, Position::of(&texts[origins[i].1], templates[i].offset));
+
+    let findings = lint::lint(&templates);
+    if matches.is_present("json") {
+        let json: Vec<_> = findings.iter().map(|f| {
+            let (file, position) = origin(f.template);
+            let mut json = f.to_json(&templates[f.template]);
+            json["file"] = file.into();
+            json["line"] = position.line.into();
+            json["column"] = position.column.into();
+            json
+        }).collect();
+        println!("{:#}", serde_json::Value::from(json));
+    } else {
+        for f in &findings {
+            let (file, position) = origin(f.template);
+            println!("{}:{}:{}: {} [{}] {}: {}", file, position.line, position.column, f.severity, f.rule, templates[f.template].name, f.message);
+        }
+    }
+
+    let fail_on = match matches.value_of("fail-on") {
+        Some("warning") => lint::Severity::Warning,
+        _ => lint::Severity::Error,
+    };
+    match findings.iter().filter(|f| f.severity >= fail_on).count() {
+        0 => Ok(()),
+        count => Err(T2eError::Lint(count)),
+    }
+}
+
+/// Formats every file in place, or only lists those that need it with --check.
+fn run_fmt(matches: &ArgMatches) -> t2e::Result<()> {
+    let check = matches.is_present("check");
+    let files: Vec<&str> = matches.values_of("FILES").into_iter().flatten().collect();
+    if files.is_empty() {
+        let text = source::StdinSource.read()?;
+        let canonical = template::canonicalize(&text)?;
+        if check {
+            return if canonical == text { Ok(()) } else { Err(T2eError::Unformatted(vec!["stdin".to_string()])) };
+        }
+        print!("{}", canonical);
+        return Ok(());
+    }
+
+    let mut unformatted = Vec::new();
+    for file in files {
+        let text = source::FileSource(PathBuf::from(file)).read()?;
+        let canonical = template::canonicalize(&text)?;
+        if canonical == text {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            unformatted.push(file.to_string());
+        } else {
+            info!("Formatting {}", file);
+            fs::write(file, canonical).map_err(|e| T2eError::io(file, e))?;
+        }
+    }
+    if unformatted.is_empty() {
+        Ok(())
+    } else {
+        Err(T2eError::Unformatted(unformatted))
+    }
 }
 
-fn get_enum_from_templates(xml: &String) -> String {
-    let mut reader = Reader::from_str(&xml);
-    reader.trim_text(true);
-    let mut count = 0;
-    let mut buf = Vec::new();
-    let mut result: Vec<String> = Vec::new();
-    loop {
-        match reader.read_event(&mut buf) {
-            Ok(Event::Start(ref e)) => {
-                match e.name() {
-                    b"template" => result.push(e.attributes().find(|x| x.as_ref().unwrap().key == b"name").unwrap().unwrap().unescape_and_decode_value(&reader).unwrap()),
-                    _ => (),
-                }
+/// Converts every file to the target format, warning about and optionally reporting every
+/// construct that doesn't translate.
+fn run_convert(matches: &ArgMatches) -> t2e::Result<()> {
+    let mut templates = Vec::new();
+    let mut report = Vec::new();
+    let mut to = matches.value_of("to");
+    for file in matches.values_of("FILES").into_iter().flatten() {
+        let text = source::FileSource(PathBuf::from(file)).read()?;
+        if vscode::is_snippets(&text) {
+            to = to.or(Some("jetbrains"));
+            for snippet in vscode::read(&text)? {
+                let (template, notes) = snippet.to_template();
+                report.extend(notes.into_iter().map(|note| format!("{}: {}: {}", file, snippet.name, note)));
+                templates.push(template);
+            }
+        } else if textmate::is_snippet(&text) {
+            to = to.or(Some("jetbrains"));
+            for (format, snippet) in textmate::read(&text)? {
+                let (template, notes) = textmate::to_template(format, &snippet);
+                report.extend(notes.into_iter().map(|note| format!("{}: {}: {}", file, snippet.name, note)));
+                templates.push(template);
             }
-            Ok(Event::Eof) => break, // exits the loop when reaching end of file
-            Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-            _ => (),
+        } else {
+            to = to.or(Some("vscode"));
+            templates.extend(parse_templates(file, &text, false)?);
         }
     }
-    for e in &mut result {
-        add_backslash_before_double_quote(e);
-    }
-    let result = format!("enum(\"{}\")", result.join("\", \""));
-    result
-}
-
-fn add_backslash_before_double_quote(s: &mut String) {
-    *s = s.replace("\"", "\\\"");
-}
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test1() {
-        let xml = r###"
-<template name="&gt;: redirect stderr to stdout (2&gt;&amp;1)" value="2&gt;&amp;1&#10;# Note the order when you want to redirect both stdout and stderr to a file.&#10;# You must first redirect stdout to a file, then stderr to stdout. Otherwise it &#10;# won't work!!!&#10;# geph-exit -singleHop :44444 &gt; /tmp/pk 2&gt;&amp;1" description="Redirect standard error to standard output." toReformat="false" toShortenFQNames="false">
-  <context>
-    <option name="SHELL_SCRIPT" value="true" />
-  </context>
-</template>
-<template name="&gt;: redirect stdout and stderr to /dev/null (&gt; /dev/null 2&gt;&amp;1)" value="&gt; /dev/null 2&gt;&amp;1" description="" toReformat="false" toShortenFQNames="false">
-  <context>
-    <option name="SHELL_SCRIPT" value="true" />
-  </context>
-</template>
-<template name="&gt;: redirect the result of multiple pipe line command (command1 | command2 | command3 &gt;&gt; file)" value="# Redirect stdout of (command1 | command2 | command3) to file&#10;# command1 | command2 | command3 &gt;&gt; file" description="command1 | command2 | command3 &gt;&gt; file" toReformat="false" toShortenFQNames="false">
-  <context>
-    <option name="SHELL_SCRIPT" value="true" />
-  </context>
-</template>
-"###;
-        let result = get_enum_from_templates(&xml.to_string());
-        assert_eq!(result, r###"enum(">: redirect stderr to stdout (2>&1)", ">: redirect stdout and stderr to /dev/null (> /dev/null 2>&1)", ">: redirect the result of multiple pipe line command (command1 | command2 | command3 >> file)")"###);
-    }
-
-
-    #[test]
-    fn test2() {
-        let xml = r###"
-<template name="mdict_after_blue_speaker" value="content: &quot;&quot;;&#10;background-image: url('data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAC4AAAAtCAYAAADRLVmZAAAIDklEQVRogcWYaXAT5xnHn3d3pV2tVtKufMi2Yh01GOgMBFKXw5gb0yaGYqIpM5C0pWk7maRtOm0y7bc2bWY6ZdJOvrQznUk6PZIpM0lUXxxDDcMRCDhtKIRQHIiR5BNbtnd17mrPfsCiEgiQLWH+3/T8n/d9frva90QNmw/AwxIfClIAoHL+gFruvrFydwgAwIc73OL05c9ZX7vo8DwlJyfOnyx3jbKD8+HO5YyrecDiXNqAEA4YbkFM9eoN8dHjb5SzTlnBhUjXCsbV3GeyuMg7PZPF1V7OWmUDFyJdX7JWrzlvslSbCxYiGK5ctQDKBC5Eur5srV5z7l7QM0IAtwYsHwoypdYsGZyPdK1iXGvPmizVpqIKEvRznD+QMHTFUNKjaXH68ufx0RN/5MMd9bOpS8wN95aESM9qm2vtaYKqLAp6RrKuZQDDSTDRdRYTXddgAWgwdPl5cfryQCYZ+RHr2X7oQZ2guc7jQqS7mXGtPUVQFUU9fCYRjpM2nyP7mw8FnQinvkKQ3LNmq3ud2eqxAUIAhg7pqYuXMslIG+fbNVJWcCHS3cLUtJwgSGfR/9id4AX6bDbbfH+muaWNgBCoUlRJRv+1j61/6u+F8mf9jQuR7vWzhS5GrPdrH9LOZYuEoUPtcmo4RVBVJnvdlndiw//8WaH8WYELgz0bmJp1x0uB5kNBmg8FnffyWc/2rtREn0fkrwxiOInsdRt/Exs++vKdeUWDC5GeTYyr5ThBciW9aYyg93H+wJSupnUpfj2aGDv9Nh/urM3N4fyBaUno96en/nMZYWaw1ax7XYh0t+Tm3Ibgw50uhOGLc01D165xvvYxIdLdYqtd34ubWbwU6BkldE0yMIJGlH1hJWVf+KyuJPcmbp5+XxWjz2Q3ZJw/oPOhYBNmso1S9gUVdMXjh/lQ0Jn1kfMLu/0WdslZyrGoFhDKqyAMHtrLetoOiMLVEQu7pK4U2rtmlXCHHzc7fkIy3j2kvaECAEAS+m+KQv9yztc+npNXb6vdeIMgOSIxduptW+2GbwIAYJR9wTGKXXwXtGFoYGjSe3woSFH2hpKgC4nz7QrZ6zb/kLQ3VMZGen+uKQmdYhfX0JUrruWurJxv11B66uJfAADoisf3Zj2MdCzwF+rY0GSD8wdUQFgVwu63kpcuh7v1tcToybWqFFVIxmsn7Qs+zvW1zPQLSnpUxM0sjpPONwAAMAy3oMLdPRzxoSDLR7oa74yz3h3nkxN9X9c1yaArljXGho58L+tx/oAqxa69BwBA2rwBgId0kLifMILey3l3fqakR8XYSO8vcj3Ws70rPXmhFwABxS7en+upcmw/GAaYGS/Hhztr5x3cMPS4oWXARNdRDvfWVxNjp/NWRiU99oyuigbJ+Dgh0rUiG+e8O/+bSQ3GEcIBI+hvzzs462l7Rxg8SCbHPzwMAMC4mvcIkZ5NtwH9gclMIjQMCAFuZn+c21aVJm8AAGAE3TTv4DNwMuNqbhOnPx1AGAGEpfqXub4m8xdnABfmxnU1PQNO1T8S8KwUKXoQAAA32xty47omDwMAIJysymtgaAIAAEK45ZGCI0S4AABAV6X8OKIBAMDQM/ktsOxZ1nhk4Hwo6KTYRU8DAKiZqZO5HsItDQAAuiaN58UxoupWXObnHZwPd9Qnxk7+iXGtHTFZXGZFHJdVaSpvEJrpuuUAAJocP5Ybx0xMAwCAoYnX5n8ex6kdttqNz5noGkoRJ+RU9KNtnD8Qz/rC4MEdZuYxRldFQ1cSv8/G+VAQIxmPDwBAU1JHy3oYKEaGoY+KwtVRVYyeUqXoDzh/YDrXpxyNbwEgEIUrH+c+ECIs38DNLK4pSd3QpOC8g7Oetk4A6AR2yV1e8uaZTqampVpXkoacHNptrWy67Zmo6hcAADLx61c5f0DHVGlSKVgBZb8iJJedvoASN8/8g3E17wRDh8TEuV9xvl2hrMeHO5dZnEtXAgDI6bFfAwBgIv/p3wp1hOEk4sMdXs7XPq7JgvawgIVIT6sk9I/Zalp2ASBIjJ951+FufTU3h7T5DmA4iaTY9Wj28EzYajd+NzbSGzFbavZhBM3mw1PbAODN9NQn79pq1+8pBygf7vDiZscrGE41mujalaynjQWEga6mjeT42dfs7ta8jVds+OgrDnfrFw1DAyn22Xcox63FtOjriVT0333WqqaVcwXOnoBiQ0dedNQ/+Yds3NAykOavXJJTQ7s5785ruW2ESM8Wu3tLL0bQKDlx/gRTvXpz1it6cFqrmlaVCg8AYBjakDh9+YauSROaHDupybHfcf7ApLXyibw8IdKzyVq96ghG0CgTH5hSUiNfzfVnfSE0V/gHXQjlKjZ89GVbTcvrGGFFcnIwmZq80Mj52sdyc2a9AM28+Y9m264Y8ZGuxvTkhUt299bfYoQVZeIDU4WgAeZ4Aio3vDB46OnkRN8HDve2frryiWUAAMmJvg/SUxfrCkEDlHBbO9dvno90NeIm+0sIM7lxE9Notj62kPW03b7tzcQHpkWh/3nW0/Y+wKp79lPSyjkXeAwzb7XXbfp+bkzXREOKXR9QUiP7HfVPvkXmb88LquQlf7bwhqGG09OfDBi6mtTVZL+upE7oavqvnD8ggXNZ0XXnfD9+px4EP5tZpRiVbVv7oAFraJl0uWoBlPle5X7wqsxfLWetsh8krFVNq5Lj544Zxv/3ZZoc0xRx/MVy1nko+3HGtaZVGDr8LZPF9ZKhK3FVHP8p593ZX84a/wPPl3ngCuo1ZQAAAABJRU5ErkJggg==');&#10;background-size: 20px;&#10;width: 20px;&#10;height: 20px;&#10;display: inline-block;&#10;background-repeat: no-repeat;&#10;vertical-align: middle;&#10;margin-right: 20px;" description=":after background" toReformat="false" toShortenFQNames="true">
-  <context>
-    <option name="CSS" value="true" />
-  </context>
-</template>
-<template name="mdict_after_red_speaker" value="content: &quot;&quot;;&#10;background-image: url('data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAC4AAAAtCAYAAADRLVmZAAAIZklEQVRogcWYa3AT1xXHz+5KWr2f1srCNrZrkGUetsECIR5JaUhKTUwITGjtpAmmCYZUTaZNpv3W0mamUybp5Is6NbZbKCFkhpDWaQIJAxkcGCxkGzBQwBhsYyM/tJJlvR8r7W4/gJgFFJBt2Tnf9vzPPee3O/fuufciPRXvwEyZzWUXAkDSqrMks50bzXZCAIBGlyOvPTh4aydhjtZrq6ivfb1t2a6RdfC9pKOyWlXat1JWWMJDUJCgAmS90vD04fHLH2azTlbBm8iOJdVKo2OuQIk/rBXgyk3ZrJU18Cayo6paWXquQKAQpNMVmFCVrVoAWQJvIjuWVStL7fnfAX3PEIC7C9bmskunW3Pa4E2kw/y80ng2X6DgZxIvx/DtVp0lGGeT7EDcG2kPDt464r3S2OhyFEymLm9quHethexcUaMsO60XyDOCBgBgWZaKMgkQoXwoxtWiYlxdAgAlMSbZ0B4c7OuJud/erjUdfVIeZKr/8WayY+XzqrJv9XxZRi9/LUoGFogIRerZ5rKrJSj/xwRf+koJrlljEOXIUECAZlk4Exy41BN1b9ipMw9nFbyZ7Fhdoyo7lZshdDrwNDlXlomIfStlhQYUEBimAokT/pvbtmmrDqWLn/QcbyY7ntqoWjAp6EzsDWJ5+2pZUel+9/lNt2Lj4TyBnP9TTfnBjz3dv0sXPynwf5CdT7+gWvCNji+dMrTNZRfbXHb1d+nbtabPv/b3znWE7gyJUD6yRb3oLwc9Fx+ZFhmDt5Cda2tUZd8Q04AGAJBj+DarzjIeoOPMpciou9V79aO9pEPPjbHqLN7OsLO4LdB/RYjyYKNqwfvNZMdqbsx9iL2kQ8cD1MgVk8D0NhDm0WayY/Um1cITWr4Emw40AADLssEIk2DlGI5UiPU5FWL9K346Vtc6ce2Ik/K/nNqQWXUWxuaym1Q80UiFWK9ZIys+ZnPZ1SkdeTt3VfEyaf7ZpZI8PXq3R9y3fe6uunqt6ZPOsHN4mSR/znSAH16cf3edK9bwxL8xiojacnGuBgCgK+wcOx8ermwgzK5UXKPLUbBZvbCf4Et5//Fe/ehF9cJXAQDQCrH+pEmS/wh0kmUgzCQ+tbnswsWi3GlBp7NduhUDWzXlvyoX5+Yc8nT/fiIZZUyS/NynZMW93M66U2e+czo4sB8AYI28qC6loRUSfXG6xDEmyVp1liQPUK0QzeoP5BGry6l87zPv/1YNU4FEmYiQV4j157n6WCK0ayDujebwJFguX/ohAAAqQQVI+nQzYzaXXdlEOgwP+18nlp077u99KcIk2NWyIsMBz4U3UppVZ0leDI98CgBgFBJbAGboIPE4k2N43Q7CfGMg7o0e8nT/gatt15o+PxXoO4EAQJUkbw9X8yTDexhgwSjSqvaSDv2sg9MsG4gxCSjG1cLanMrdrd6rD3TGgfjEyyGaYstEhKqJ7FiS8u8gzNduRN0BHoKCFMXrZx28Xlt1sMXdhR/19RwDANigMta2kJ1rU7pVZ/Fci7qcKCCg5Ul+zR07mgj2AwDIMNw06+D34KgNSuMGe3Cwj49gUIAr/sjVyUSoGwBAignmc/1BOt4PACBB+QXfC3jKnJT/SwAANU9cwvVH2aQTAECM8rVcP80yPgAADEFF3yu4AMF0AAAJho5x/RggYgAAGtg4148CkjrLst8buM1lVy+V5m0GABhLBNu4mgQTlAAAhGnKxfXzUUwLABBnkxMz21nSWKPLUUDwJbtrlMa6uQKlYIjyUSOJ4AOLsBhXVwIAeJPRk1y/EhOWAACEaKp31r+4GOPXbFYv2l6Iq4ROyk+d8N18zqqzBFL6P91dNfOFOdIQTbETdNSW8ttcdtQg1BYBAATo+PFZ/+I0y4x0hp0jw3H/t85EwGrVWbxcfYl4TgsCAI7Q0HnuC0lRwc+1fAnmS0aZMEN9Nuvg9VpTKwC0LpPkP6J9MXG9tUZVRvjpGNsb82x9RjHvvpYvUOwCAOiOjF636iwMOkoFEukKYMjdLQyCADUTL/Cw/Xfi+r+rlcYXaJaFoxM9f9qlWzGQ0vaSjvJVssLlAAC34xN/BgBA20NDB9IlEqF8pNHlKGwgzC53IkzPFHAL2flsV9g5ulFV9iKCAHzhu364LqdyNzfGKCQ+EaF8pDs84k4dnnlb1IteP+TpHizCVdukGK7kDhBj/OcAoPls8PbhTeqFtdkAbXQ5CtU80bsSTGAowlXL67UmJYYgEKTj7Je+nvdqNRUPbLw+9lx892eaygVJloELkZFfVEruHg0yvp446b/lWKeYt3yqwKkT0AHPhTdfzVn6t5Q/xiTAHhq6dDPm2bqDMPdyx7SQnc9s1ZSfkGM48pXvxqmfKEt/lNIyXpzrFPPM04UHAEiyzJ324GB/mKHI8WSkzZOM/NWqs3jWyh/o+tBCdq5drzR8Jcdw5HJkbLwv7l3P1Sd9ITRV+CddCHHtoOfiOxtVZe/LMSFyI+YOtQX6DQ2EeZQbM+kGdO/Ld0x2XCbWRDoMbYH+S7Waig/kmBC5HBkbTwcNMMUTULbh97m7Nh/3956p0yzp+aH8B+UAAMf9vWdOBwfmpIMGmMalJ8Dkpk1qqjSRDoOKJ34LR3h5CkxomC/UzJ/Due29EhnzdoWdDfVa05HH5ZtW55zKghWi/HUvqRf/kusLMxTbHR7puxUb3/OatqplsTj3iXmm3fInC59g6Ntng7f7KJYO+el4T4COnQrQ8X9ZdZbYKllRxnWnNVW49iT4yfxVMrGsbWuftGCjTCKSrVoAWb5XeRw8mQhdz2atrB8k1inmmY/5bpykWea+bzwZoe9QvjezWWdG9uPVytJn97vPvzYXV75FMXRgiPL9dgdh7slmjf8DWcaIBHqkimUAAAAASUVORK5CYII=');&#10;background-size: 20px;&#10;width: 20px;&#10;height: 20px;&#10;display: inline-block;&#10;background-repeat: no-repeat;&#10;vertical-align: middle;&#10;margin-right: 20px;" description=":after background" toReformat="false" toShortenFQNames="true">
-  <context>
-    <option name="CSS" value="true" />
-  </context>
-</template>
-"###;
-        let result = get_enum_from_templates(&xml.to_string());
-        assert_eq!(result, r###"enum("mdict_after_blue_speaker", "mdict_after_red_speaker")"###);
-    }
-
-
-    #[test]
-    fn test3() {
-        let xml = r###"
-<template name="mdict_after_&quot;blue'_speaker" value="content: &quot;&quot;;&#10;background-image: url('data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAC4AAAAtCAYAAADRLVmZAAAIDklEQVRogcWYaXAT5xnHn3d3pV2tVtKufMi2Yh01GOgMBFKXw5gb0yaGYqIpM5C0pWk7maRtOm0y7bc2bWY6ZdJOvrQznUk6PZIpM0lUXxxDDcMRCDhtKIRQHIiR5BNbtnd17mrPfsCiEgiQLWH+3/T8n/d9frva90QNmw/AwxIfClIAoHL+gFruvrFydwgAwIc73OL05c9ZX7vo8DwlJyfOnyx3jbKD8+HO5YyrecDiXNqAEA4YbkFM9eoN8dHjb5SzTlnBhUjXCsbV3GeyuMg7PZPF1V7OWmUDFyJdX7JWrzlvslSbCxYiGK5ctQDKBC5Eur5srV5z7l7QM0IAtwYsHwoypdYsGZyPdK1iXGvPmizVpqIKEvRznD+QMHTFUNKjaXH68ufx0RN/5MMd9bOpS8wN95aESM9qm2vtaYKqLAp6RrKuZQDDSTDRdRYTXddgAWgwdPl5cfryQCYZ+RHr2X7oQZ2guc7jQqS7mXGtPUVQFUU9fCYRjpM2nyP7mw8FnQinvkKQ3LNmq3ud2eqxAUIAhg7pqYuXMslIG+fbNVJWcCHS3cLUtJwgSGfR/9id4AX6bDbbfH+muaWNgBCoUlRJRv+1j61/6u+F8mf9jQuR7vWzhS5GrPdrH9LOZYuEoUPtcmo4RVBVJnvdlndiw//8WaH8WYELgz0bmJp1x0uB5kNBmg8FnffyWc/2rtREn0fkrwxiOInsdRt/Exs++vKdeUWDC5GeTYyr5ThBciW9aYyg93H+wJSupnUpfj2aGDv9Nh/urM3N4fyBaUno96en/nMZYWaw1ax7XYh0t+Tm3Ibgw50uhOGLc01D165xvvYxIdLdYqtd34ubWbwU6BkldE0yMIJGlH1hJWVf+KyuJPcmbp5+XxWjz2Q3ZJw/oPOhYBNmso1S9gUVdMXjh/lQ0Jn1kfMLu/0WdslZyrGoFhDKqyAMHtrLetoOiMLVEQu7pK4U2rtmlXCHHzc7fkIy3j2kvaECAEAS+m+KQv9yztc+npNXb6vdeIMgOSIxduptW+2GbwIAYJR9wTGKXXwXtGFoYGjSe3woSFH2hpKgC4nz7QrZ6zb/kLQ3VMZGen+uKQmdYhfX0JUrruWurJxv11B66uJfAADoisf3Zj2MdCzwF+rY0GSD8wdUQFgVwu63kpcuh7v1tcToybWqFFVIxmsn7Qs+zvW1zPQLSnpUxM0sjpPONwAAMAy3oMLdPRzxoSDLR7oa74yz3h3nkxN9X9c1yaArljXGho58L+tx/oAqxa69BwBA2rwBgId0kLifMILey3l3fqakR8XYSO8vcj3Ws70rPXmhFwABxS7en+upcmw/GAaYGS/Hhztr5x3cMPS4oWXARNdRDvfWVxNjp/NWRiU99oyuigbJ+Dgh0rUiG+e8O/+bSQ3GEcIBI+hvzzs462l7Rxg8SCbHPzwMAMC4mvcIkZ5NtwH9gclMIjQMCAFuZn+c21aVJm8AAGAE3TTv4DNwMuNqbhOnPx1AGAGEpfqXub4m8xdnABfmxnU1PQNO1T8S8KwUKXoQAAA32xty47omDwMAIJysymtgaAIAAEK45ZGCI0S4AABAV6X8OKIBAMDQM/ktsOxZ1nhk4Hwo6KTYRU8DAKiZqZO5HsItDQAAuiaN58UxoupWXObnHZwPd9Qnxk7+iXGtHTFZXGZFHJdVaSpvEJrpuuUAAJocP5Ybx0xMAwCAoYnX5n8ex6kdttqNz5noGkoRJ+RU9KNtnD8Qz/rC4MEdZuYxRldFQ1cSv8/G+VAQIxmPDwBAU1JHy3oYKEaGoY+KwtVRVYyeUqXoDzh/YDrXpxyNbwEgEIUrH+c+ECIs38DNLK4pSd3QpOC8g7Oetk4A6AR2yV1e8uaZTqampVpXkoacHNptrWy67Zmo6hcAADLx61c5f0DHVGlSKVgBZb8iJJedvoASN8/8g3E17wRDh8TEuV9xvl2hrMeHO5dZnEtXAgDI6bFfAwBgIv/p3wp1hOEk4sMdXs7XPq7JgvawgIVIT6sk9I/Zalp2ASBIjJ951+FufTU3h7T5DmA4iaTY9Wj28EzYajd+NzbSGzFbavZhBM3mw1PbAODN9NQn79pq1+8pBygf7vDiZscrGE41mujalaynjQWEga6mjeT42dfs7ta8jVds+OgrDnfrFw1DAyn22Xcox63FtOjriVT0333WqqaVcwXOnoBiQ0dedNQ/+Yds3NAykOavXJJTQ7s5785ruW2ESM8Wu3tLL0bQKDlx/gRTvXpz1it6cFqrmlaVCg8AYBjakDh9+YauSROaHDupybHfcf7ApLXyibw8IdKzyVq96ghG0CgTH5hSUiNfzfVnfSE0V/gHXQjlKjZ89GVbTcvrGGFFcnIwmZq80Mj52sdyc2a9AM28+Y9m264Y8ZGuxvTkhUt299bfYoQVZeIDU4WgAeZ4Aio3vDB46OnkRN8HDve2frryiWUAAMmJvg/SUxfrCkEDlHBbO9dvno90NeIm+0sIM7lxE9Notj62kPW03b7tzcQHpkWh/3nW0/Y+wKp79lPSyjkXeAwzb7XXbfp+bkzXREOKXR9QUiP7HfVPvkXmb88LquQlf7bwhqGG09OfDBi6mtTVZL+upE7oavqvnD8ggXNZ0XXnfD9+px4EP5tZpRiVbVv7oAFraJl0uWoBlPle5X7wqsxfLWetsh8krFVNq5Lj544Zxv/3ZZoc0xRx/MVy1nko+3HGtaZVGDr8LZPF9ZKhK3FVHP8p593ZX84a/wPPl3ngCuo1ZQAAAABJRU5ErkJggg==');&#10;background-size: 20px;&#10;width: 20px;&#10;height: 20px;&#10;display: inline-block;&#10;background-repeat: no-repeat;&#10;vertical-align: middle;&#10;margin-right: 20px;" description=":after background" toReformat="false" toShortenFQNames="true">
-  <context>
-    <option name="CSS" value="true" />
-  </context>
-</template>
-<template name="mdict_after_red_speaker" value="content: &quot;&quot;;&#10;background-image: url('data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAC4AAAAtCAYAAADRLVmZAAAIZklEQVRogcWYa3AT1xXHz+5KWr2f1srCNrZrkGUetsECIR5JaUhKTUwITGjtpAmmCYZUTaZNpv3W0mamUybp5Is6NbZbKCFkhpDWaQIJAxkcGCxkGzBQwBhsYyM/tJJlvR8r7W4/gJgFFJBt2Tnf9vzPPee3O/fuufciPRXvwEyZzWUXAkDSqrMks50bzXZCAIBGlyOvPTh4aydhjtZrq6ivfb1t2a6RdfC9pKOyWlXat1JWWMJDUJCgAmS90vD04fHLH2azTlbBm8iOJdVKo2OuQIk/rBXgyk3ZrJU18Cayo6paWXquQKAQpNMVmFCVrVoAWQJvIjuWVStL7fnfAX3PEIC7C9bmskunW3Pa4E2kw/y80ng2X6DgZxIvx/DtVp0lGGeT7EDcG2kPDt464r3S2OhyFEymLm9quHethexcUaMsO60XyDOCBgBgWZaKMgkQoXwoxtWiYlxdAgAlMSbZ0B4c7OuJud/erjUdfVIeZKr/8WayY+XzqrJv9XxZRi9/LUoGFogIRerZ5rKrJSj/xwRf+koJrlljEOXIUECAZlk4Exy41BN1b9ipMw9nFbyZ7Fhdoyo7lZshdDrwNDlXlomIfStlhQYUEBimAokT/pvbtmmrDqWLn/QcbyY7ntqoWjAp6EzsDWJ5+2pZUel+9/lNt2Lj4TyBnP9TTfnBjz3dv0sXPynwf5CdT7+gWvCNji+dMrTNZRfbXHb1d+nbtabPv/b3znWE7gyJUD6yRb3oLwc9Fx+ZFhmDt5Cda2tUZd8Q04AGAJBj+DarzjIeoOPMpciou9V79aO9pEPPjbHqLN7OsLO4LdB/RYjyYKNqwfvNZMdqbsx9iL2kQ8cD1MgVk8D0NhDm0WayY/Um1cITWr4Emw40AADLssEIk2DlGI5UiPU5FWL9K346Vtc6ce2Ik/K/nNqQWXUWxuaym1Q80UiFWK9ZIys+ZnPZ1SkdeTt3VfEyaf7ZpZI8PXq3R9y3fe6uunqt6ZPOsHN4mSR/znSAH16cf3edK9bwxL8xiojacnGuBgCgK+wcOx8ermwgzK5UXKPLUbBZvbCf4Et5//Fe/ehF9cJXAQDQCrH+pEmS/wh0kmUgzCQ+tbnswsWi3GlBp7NduhUDWzXlvyoX5+Yc8nT/fiIZZUyS/NynZMW93M66U2e+czo4sB8AYI28qC6loRUSfXG6xDEmyVp1liQPUK0QzeoP5BGry6l87zPv/1YNU4FEmYiQV4j157n6WCK0ayDujebwJFguX/ohAAAqQQVI+nQzYzaXXdlEOgwP+18nlp077u99KcIk2NWyIsMBz4U3UppVZ0leDI98CgBgFBJbAGboIPE4k2N43Q7CfGMg7o0e8nT/gatt15o+PxXoO4EAQJUkbw9X8yTDexhgwSjSqvaSDv2sg9MsG4gxCSjG1cLanMrdrd6rD3TGgfjEyyGaYstEhKqJ7FiS8u8gzNduRN0BHoKCFMXrZx28Xlt1sMXdhR/19RwDANigMta2kJ1rU7pVZ/Fci7qcKCCg5Ul+zR07mgj2AwDIMNw06+D34KgNSuMGe3Cwj49gUIAr/sjVyUSoGwBAignmc/1BOt4PACBB+QXfC3jKnJT/SwAANU9cwvVH2aQTAECM8rVcP80yPgAADEFF3yu4AMF0AAAJho5x/RggYgAAGtg4148CkjrLst8buM1lVy+V5m0GABhLBNu4mgQTlAAAhGnKxfXzUUwLABBnkxMz21nSWKPLUUDwJbtrlMa6uQKlYIjyUSOJ4AOLsBhXVwIAeJPRk1y/EhOWAACEaKp31r+4GOPXbFYv2l6Iq4ROyk+d8N18zqqzBFL6P91dNfOFOdIQTbETdNSW8ttcdtQg1BYBAATo+PFZ/+I0y4x0hp0jw3H/t85EwGrVWbxcfYl4TgsCAI7Q0HnuC0lRwc+1fAnmS0aZMEN9Nuvg9VpTKwC0LpPkP6J9MXG9tUZVRvjpGNsb82x9RjHvvpYvUOwCAOiOjF636iwMOkoFEukKYMjdLQyCADUTL/Cw/Xfi+r+rlcYXaJaFoxM9f9qlWzGQ0vaSjvJVssLlAAC34xN/BgBA20NDB9IlEqF8pNHlKGwgzC53IkzPFHAL2flsV9g5ulFV9iKCAHzhu364LqdyNzfGKCQ+EaF8pDs84k4dnnlb1IteP+TpHizCVdukGK7kDhBj/OcAoPls8PbhTeqFtdkAbXQ5CtU80bsSTGAowlXL67UmJYYgEKTj7Je+nvdqNRUPbLw+9lx892eaygVJloELkZFfVEruHg0yvp446b/lWKeYt3yqwKkT0AHPhTdfzVn6t5Q/xiTAHhq6dDPm2bqDMPdyx7SQnc9s1ZSfkGM48pXvxqmfKEt/lNIyXpzrFPPM04UHAEiyzJ324GB/mKHI8WSkzZOM/NWqs3jWyh/o+tBCdq5drzR8Jcdw5HJkbLwv7l3P1Sd9ITRV+CddCHHtoOfiOxtVZe/LMSFyI+YOtQX6DQ2EeZQbM+kGdO/Ld0x2XCbWRDoMbYH+S7Waig/kmBC5HBkbTwcNMMUTULbh97m7Nh/3956p0yzp+aH8B+UAAMf9vWdOBwfmpIMGmMalJ8Dkpk1qqjSRDoOKJ34LR3h5CkxomC/UzJ/Due29EhnzdoWdDfVa05HH5ZtW55zKghWi/HUvqRf/kusLMxTbHR7puxUb3/OatqplsTj3iXmm3fInC59g6Ntng7f7KJYO+el4T4COnQrQ8X9ZdZbYKllRxnWnNVW49iT4yfxVMrGsbWuftGCjTCKSrVoAWb5XeRw8mQhdz2atrB8k1inmmY/5bpykWea+bzwZoe9QvjezWWdG9uPVytJn97vPvzYXV75FMXRgiPL9dgdh7slmjf8DWcaIBHqkimUAAAAASUVORK5CYII=');&#10;background-size: 20px;&#10;width: 20px;&#10;height: 20px;&#10;display: inline-block;&#10;background-repeat: no-repeat;&#10;vertical-align: middle;&#10;margin-right: 20px;" description=":after background" toReformat="false" toShortenFQNames="true">
-  <context>
-    <option name="CSS" value="true" />
-  </context>
-</template>
-"###;
-        let result = get_enum_from_templates(&xml.to_string());
-        assert_eq!(result, "enum(\"mdict_after_\\\"blue'_speaker\", \"mdict_after_red_speaker\")");
-    }
-
-    #[test]
-    fn test4() {
-        let data = r#"g
-3
-g3"#;
-        let result = get_enum_from_lines(&data.to_string());
-        assert_eq!(result, "enum(\"g\", \"3\", \"g3\")")
-    }
-
-    #[test]
-    fn test5() {
-        let data = r#"
-g
-3
-g3
---------------------------------
-Nothing: the first occurence in every line will be replaced
-g: all occurences will be replaced
-3: the 3rd occurrence will be replaced (count from 1)
-g3 or 3g: occurrence 3, 4, 5, ... will be replaced"#;
-        let result = get_enum_from_lines(&data.to_string());
-        assert_eq!(result, "enum(\"\", \"g\", \"3\", \"g3\", \"--------------------------------\", \"Nothing: the first occurence in every line will be replaced\", \"g: all occurences will be replaced\", \"3: the 3rd occurrence will be replaced (count from 1)\", \"g3 or 3g: occurrence 3, 4, 5, ... will be replaced\")")
-    }
-
-    #[test]
-    fn test6() {
-        let data = r#"
-g
-3
-g"3
---------------------------------
-No"thing: the first occurence in every line will be replaced
-g: all occurences will be replaced
-3: the 3rd occurrence will be replaced (count from 1)
-g3 or 3g: occurrence 3, 4, 5, ... will be replaced"#;
-        let result = get_enum_from_lines(&data.to_string());
-        assert_eq!(result, "enum(\"\", \"g\", \"3\", \"g\\\"3\", \"--------------------------------\", \"No\\\"thing: the first occurence in every line will be replaced\", \"g: all occurences will be replaced\", \"3: the 3rd occurrence will be replaced (count from 1)\", \"g3 or 3g: occurrence 3, 4, 5, ... will be replaced\")")
+
+    let result = if to == Some("vscode") {
+        let snippets: Vec<vscode::VsSnippet> = templates.iter().map(|t| {
+            let (snippet, notes) = vscode::VsSnippet::from_template(t);
+            report.extend(notes.into_iter().map(|note| format!("{}: {}", t.name, note)));
+            snippet
+        }).collect();
+        vscode::to_file(&snippets)
+    } else {
+        emit::TemplateSets.emit_templates(&templates)
+    };
+    for line in &report {
+        warn!("{}", line);
+    }
+    sink::from_arg(matches.value_of("output").unwrap(), false).write(&result)?;
+    match matches.value_of("report") {
+        Some(target) => sink::from_arg(target, false).write(&report.join("\n")),
+        None => Ok(()),
+    }
+}
+
+/// Reads the templates of one file, warning about the ones skipped.
+fn read_templates(file: &str, strict: bool) -> t2e::Result<Vec<template::Template>> {
+    read_templates_with_text(file, strict).map(|(_, templates)| templates)
+}
+
+fn read_templates_with_text(file: &str, strict: bool) -> t2e::Result<(String, Vec<template::Template>)> {
+    let text = source::FileSource(PathBuf::from(file)).read()?;
+    let templates = parse_templates(file, &text, strict)?;
+    Ok((text, templates))
+}
+
+fn parse_templates(file: &str, text: &str, strict: bool) -> t2e::Result<Vec<template::Template>> {
+    let (templates, skipped) = template::read(text, strict)?;
+    for s in &skipped {
+        warn!("Skipped template at byte {} of {}: {}", s.offset, file, s.error);
+    }
+    Ok(templates)
+}
+
+/// Prints every IDE configuration found, with its template files indented below it.
+fn run_ides() -> t2e::Result<()> {
+    for product in discover::products(&discover::Roots::from_env()) {
+        println!("{}\t{}", product.full_name(), product.config_dir.display());
+        for path in product.templates() {
+            println!("    {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Prints the expression in canonical form followed by the value it evaluates to.
+fn run_expr(matches: &ArgMatches) -> t2e::Result<()> {
+    let text = matches.value_of("EXPRESSION").unwrap();
+    let expr = expr::parse(text)?;
+    println!("{}", expr);
+
+    let problems = eval::validate(&expr);
+    for problem in &problems {
+        warn!("{}", problem);
+    }
+
+    let mut context = eval::Context::new();
+    for var in matches.values_of("var").into_iter().flatten() {
+        let (name, value) = var.split_at(var.find('=').unwrap_or(var.len()));
+        context.variables.insert(name.to_string(), value.trim_start_matches('=').to_string());
+    }
+    match eval::eval(&expr, &context) {
+        Ok(value) => println!("{}", value),
+        Err(e) => trace!("No preview: {}", e),
+    }
+
+    match problems.into_iter().find(|p| !matches!(p, eval::EvalError::NeedsIde(_))) {
+        Some(problem) => Err(T2eError::Eval(problem)),
+        None => Ok(()),
     }
 }
@@ -0,0 +1,172 @@
+//! Combining template files that overlap into one template set per group.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+use crate::diff;
+use crate::template::{self, Template};
+
+/// Which of several templates with the same name in the same group to keep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prefer {
+    /// The one from the first input.
+    Ours,
+    /// The one from the last input.
+    Theirs,
+    /// The one from the most recently modified input; the later input on a tie.
+    Newest,
+}
+
+impl FromStr for Prefer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Prefer, String> {
+        match s {
+            "ours" => Ok(Prefer::Ours),
+            "theirs" => Ok(Prefer::Theirs),
+            "newest" => Ok(Prefer::Newest),
+            _ => Err(format!("Expected ours, theirs or newest, not {}", s)),
+        }
+    }
+}
+
+/// The templates of one file.
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    /// Where the templates come from, for reports.
+    pub label: String,
+    pub templates: Vec<Template>,
+    pub modified: Option<SystemTime>,
+}
+
+/// Templates with the same name and group but different values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub group: String,
+    pub name: String,
+    /// Labels of the inputs that define the template, in input order.
+    pub labels: Vec<String>,
+    /// Label of the input whose template was kept.
+    pub kept: String,
+    /// What differs between the definitions, as named by [`diff::Change::what`].
+    pub differences: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" in group \"{}\" differs between {} in {}; kept {}",
+            self.name, self.group, self.labels.join(", "), self.differences.join(", "), self.kept)
+    }
+}
+
+/// Merges the inputs into template sets sorted by group, each sorted by template name.
+///
+/// Identical duplicates are dropped silently; duplicates that differ in anything but where they
+/// were read from are resolved with `prefer` and reported. Context options are sorted as `fmt`
+/// sorts them.
+pub fn merge(inputs: &[Input], prefer: Prefer) -> (Vec<(String, Vec<Template>)>, Vec<Conflict>) {
+    // Every definition of each group and name, with the index of its input, in input order.
+    let mut definitions: Vec<Vec<(usize, &Template)>> = Vec::new();
+    for (i, input) in inputs.iter().enumerate() {
+        for template in &input.templates {
+            match definitions.iter_mut().find(|found| found[0].1.group == template.group && found[0].1.name == template.name) {
+                Some(found) => found.push((i, template)),
+                None => definitions.push(vec![(i, template)]),
+            }
+        }
+    }
+    definitions.sort_by(|a, b| (&a[0].1.group, &a[0].1.name).cmp(&(&b[0].1.group, &b[0].1.name)));
+
+    let mut sets: Vec<(String, Vec<Template>)> = Vec::new();
+    let mut conflicts = Vec::new();
+    for found in definitions {
+        let (group, name) = (&found[0].1.group, &found[0].1.name);
+        let (kept, template) = match prefer {
+            Prefer::Ours => found[0],
+            Prefer::Theirs => found[found.len() - 1],
+            Prefer::Newest => *found.iter().max_by_key(|(i, _)| inputs[*i].modified).unwrap(),
+        };
+        let mut differences: Vec<String> = Vec::new();
+        for change in found.iter().flat_map(|(_, t)| diff::changes(template, t)) {
+            if !differences.contains(&change.what) {
+                differences.push(change.what);
+            }
+        }
+        if !differences.is_empty() {
+            conflicts.push(Conflict {
+                group: group.to_string(),
+                name: name.to_string(),
+                labels: found.iter().map(|(i, _)| inputs[*i].label.clone()).collect(),
+                kept: inputs[kept].label.clone(),
+                differences,
+            });
+        }
+        let template = Template { offset: 0, ..template.clone() };
+        match sets.last_mut() {
+            Some((g, templates)) if g == group => templates.push(template),
+            _ => sets.push((group.to_string(), vec![template])),
+        }
+    }
+    for (_, templates) in &mut sets {
+        template::sort_set(templates);
+    }
+    (sets, conflicts)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn template(group: &str, name: &str, value: &str) -> Template {
+        Template { group: group.to_string(), name: name.to_string(), value: value.to_string(), offset: 7, ..Default::default() }
+    }
+
+    #[test]
+    fn test1() {
+        let inputs = vec![
+            Input {
+                label: "a.xml".to_string(),
+                templates: vec![template("sh", "z", "1"), template("sh", "b", "old"), template("css", "c", "")],
+                modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(20)),
+            },
+            Input {
+                label: "b.xml".to_string(),
+                templates: vec![template("sh", "b", "new"), template("sh", "z", "1")],
+                modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(10)),
+            },
+        ];
+        let (sets, conflicts) = merge(&inputs, Prefer::Ours);
+        let names: Vec<(&str, Vec<&str>)> = sets.iter().map(|(g, t)| (g.as_str(), t.iter().map(|t| t.value.as_str()).collect())).collect();
+        assert_eq!(names, vec![("css", vec![""]), ("sh", vec!["old", "1"])]);
+        assert_eq!(sets[1].1[0].offset, 0);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].to_string(), "\"b\" in group \"sh\" differs between a.xml, b.xml in value; kept a.xml");
+
+        assert_eq!(merge(&inputs, Prefer::Theirs).0[1].1[0].value, "new");
+        assert_eq!(merge(&inputs, Prefer::Newest).0[1].1[0].value, "old");
+        assert_eq!("newest".parse::<Prefer>(), Ok(Prefer::Newest));
+        assert!("mine".parse::<Prefer>().is_err());
+    }
+
+    #[test]
+    fn test2() {
+        let xml = |description: &str, attributes: &str, options: &str| format!(
+            r#"<templateSet group="sh"><template name="x" value="1" description="{}"{}><context>{}</context></template></templateSet>"#,
+            description, attributes, options);
+        let ours = xml("a", "", r#"<option name="SHELL_SCRIPT" value="true"/><option name="CSS" value="true"/>"#);
+        let theirs = xml("b", r#" deactivated="true""#, r#"<option name="SHELL_SCRIPT" value="true"/>"#);
+        let input = |label: &str, xml: &str| Input { label: label.to_string(), templates: template::read(xml, true).unwrap().0, modified: None };
+        let inputs = vec![input("a.xml", &ours), input("b.xml", &theirs)];
+        let (sets, conflicts) = merge(&inputs, Prefer::Ours);
+        assert_eq!(conflicts[0].differences, vec!["description", "deactivated", "context CSS"]);
+        let contexts: Vec<&str> = sets[0].1[0].contexts.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(contexts, vec!["CSS", "SHELL_SCRIPT"]);
+        let merged = template::set_to_xml(&sets[0].0, &sets[0].1);
+        assert_eq!(template::canonicalize(&merged).unwrap(), merged);
+
+        let (_, conflicts) = merge(&[input("a.xml", &ours), input("c.xml", &ours)], Prefer::Ours);
+        assert!(conflicts.is_empty());
+    }
+}
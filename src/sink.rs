@@ -0,0 +1,108 @@
+use clipboard::{ClipboardContext, ClipboardProvider};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use crate::error::{Result, T2eError};
+
+/// Somewhere the generated result goes.
+pub trait Sink {
+    fn write(&mut self, result: &str) -> Result<()>;
+}
+
+/// The system clipboard.
+pub struct ClipboardSink;
+
+impl Sink for ClipboardSink {
+    fn write(&mut self, result: &str) -> Result<()> {
+        let mut clipboard: ClipboardContext = ClipboardProvider::new().map_err(clipboard_error)?;
+        clipboard.set_contents(result.to_owned()).map_err(clipboard_error)
+    }
+}
+
+/// Standard output, followed by a newline.
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn write(&mut self, result: &str) -> Result<()> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        writeln!(out, "{}", result).map_err(|e| T2eError::io("stdout", e))
+    }
+}
+
+/// A file, either overwritten or appended to.
+///
+/// A trailing newline is written so that repeated appends produce one result per line.
+pub struct FileSink {
+    pub path: PathBuf,
+    pub append: bool,
+}
+
+impl Sink for FileSink {
+    fn write(&mut self, result: &str) -> Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", result))
+            .map_err(|e| T2eError::io(&self.path.display().to_string(), e))
+    }
+}
+
+/// Turns a command line output argument (`clipboard`, `stdout` or a path) into a sink.
+pub fn from_arg(output: &str, append: bool) -> Box<dyn Sink> {
+    match output {
+        "clipboard" => Box::new(ClipboardSink),
+        "stdout" | "-" => Box::new(StdoutSink),
+        path => Box::new(FileSink { path: PathBuf::from(path), append }),
+    }
+}
+
+/// Writes several files under `dir`, creating directories as needed and overwriting the files.
+pub fn write_files(dir: &Path, files: &[(PathBuf, String)]) -> Result<()> {
+    for (path, content) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| T2eError::io(&parent.display().to_string(), e))?;
+        }
+        fs::write(&path, content).map_err(|e| T2eError::io(&path.display().to_string(), e))?;
+    }
+    Ok(())
+}
+
+fn clipboard_error(e: Box<dyn std::error::Error>) -> T2eError {
+    T2eError::Clipboard(e.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test1() {
+        let path = env::temp_dir().join("t2e-sink-test1.txt");
+        let path = path.to_str().unwrap();
+        from_arg(path, false).write("enum(\"a\")").unwrap();
+        from_arg(path, true).write("enum(\"b\")").unwrap();
+        let written = fs::read_to_string(path).unwrap();
+        from_arg(path, false).write("enum(\"c\")").unwrap();
+        let overwritten = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(written, "enum(\"a\")\nenum(\"b\")\n");
+        assert_eq!(overwritten, "enum(\"c\")\n");
+    }
+
+    #[test]
+    fn test2() {
+        let dir = env::temp_dir().join("t2e-sink-test2");
+        write_files(&dir, &[(PathBuf::from("sh-mode/for"), "x".to_string()), (PathBuf::from("all.snippets"), "y".to_string())]).unwrap();
+        let nested = fs::read_to_string(dir.join("sh-mode/for")).unwrap();
+        let top = fs::read_to_string(dir.join("all.snippets")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((nested.as_str(), top.as_str()), ("x", "y"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Turning a code snippet with marked placeholders into a live template.
+//!
+//! Placeholders are written `${name}`, `<<name>>` or `$NAME$`. Any of them may carry the values
+//! to choose from, `${color=red|green|blue}`, which become an `enum()` expression.
+
+use regex::Regex;
+use crate::emit::{Emitter, JetBrainsEnum};
+use crate::template::{Variable, PREDEFINED};
+
+/// Converts `snippet` to a template value and its variables, in order of first appearance.
+///
+/// Placeholders become `$name$`; any other `$` is doubled so the IDE keeps it literal. A
+/// placeholder used more than once gets one variable, whose values come from whichever use
+/// lists them.
+pub fn parse(snippet: &str) -> (String, Vec<Variable>) {
+    let placeholder = Regex::new(r"\$\{([^}\n]*)\}|<<([A-Za-z_]\w*(?:=[^>\n]*)?)>>|\$([A-Za-z_]\w*(?:=[^$\n]*)?)\$").unwrap();
+    let annotated = Regex::new(r"^([A-Za-z_]\w*)(?:=(.*))?$").unwrap();
+    let mut value = String::new();
+    let mut variables: Vec<Variable> = Vec::new();
+    let mut last = 0;
+    for captures in placeholder.captures_iter(snippet) {
+        let whole = captures.get(0).unwrap();
+        let inner = (1..=3).find_map(|i| captures.get(i)).unwrap().as_str();
+        let parts = match annotated.captures(inner) {
+            Some(parts) => parts,
+            None => continue,
+        };
+        value.push_str(&snippet[last..whole.start()].replace('$', "$$"));
+        last = whole.end();
+
+        let name = &parts[1];
+        value.push_str(&format!("${}$", name));
+        if PREDEFINED.contains(&name) {
+            continue;
+        }
+        let index = match variables.iter().position(|v| v.name == name) {
+            Some(index) => index,
+            None => {
+                variables.push(Variable { name: name.to_string(), always_stop_at: true, ..Default::default() });
+                variables.len() - 1
+            }
+        };
+        if let Some(values) = parts.get(2) {
+            let items: Vec<String> = values.as_str().split('|').map(|v| v.trim().to_string()).collect();
+            if variables[index].expression.is_empty() {
+                variables[index].expression = JetBrainsEnum.emit(&items);
+            }
+        }
+    }
+    value.push_str(&snippet[last..].replace('$', "$$"));
+    (value, variables)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        let (value, variables) = parse("for ${item} in <<items>>; do\n  echo \"$$item $item\" >&${fd=1|2}\ndone$END$");
+        assert_eq!(value, "for $item$ in $items$; do\n  echo \"$$$$item $$item\" >&$fd$\ndone$END$");
+        let names: Vec<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["item", "items", "fd"]);
+        assert_eq!(variables[2].expression, "enum(\"1\", \"2\")");
+        assert!(variables[0].expression.is_empty() && variables[0].always_stop_at);
+    }
+
+    #[test]
+    fn test2() {
+        let (value, variables) = parse("$TYPE$ a = <<TYPE=int|long>>; ${not a name} << >>");
+        assert_eq!(value, "$TYPE$ a = $TYPE$; $${not a name} << >>");
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].expression, "enum(\"int\", \"long\")");
+    }
+
+    #[test]
+    fn test3() {
+        let (value, variables) = parse("cout << x >> y;\nx = y << 2 >> z; << name >>");
+        assert_eq!(value, "cout << x >> y;\nx = y << 2 >> z; << name >>");
+        assert!(variables.is_empty());
+    }
+}
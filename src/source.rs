@@ -0,0 +1,214 @@
+use clipboard::{ClipboardContext, ClipboardProvider};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use log::debug;
+use crate::discover;
+use crate::error::{Result, T2eError};
+
+/// Somewhere the text to convert comes from.
+pub trait Source {
+    fn read(&mut self) -> Result<String>;
+}
+
+/// The system clipboard.
+pub struct ClipboardSource;
+
+impl Source for ClipboardSource {
+    fn read(&mut self) -> Result<String> {
+        debug!("Reading clipboard");
+        let mut clipboard: ClipboardContext = ClipboardProvider::new().map_err(clipboard_error)?;
+        clipboard.get_contents().map_err(clipboard_error)
+    }
+}
+
+/// Standard input, read until EOF.
+pub struct StdinSource;
+
+impl Source for StdinSource {
+    fn read(&mut self) -> Result<String> {
+        debug!("Reading stdin");
+        let mut buf = Vec::new();
+        io::stdin().read_to_end(&mut buf).map_err(|e| T2eError::io("stdin", e))?;
+        decode("stdin", buf)
+    }
+}
+
+/// A single file on disk.
+pub struct FileSource(pub PathBuf);
+
+impl Source for FileSource {
+    fn read(&mut self) -> Result<String> {
+        debug!("Reading {}", self.0.display());
+        let path = self.0.display().to_string();
+        let buf = fs::read(&self.0).map_err(|e| T2eError::io(&path, e))?;
+        decode(&path, buf)
+    }
+}
+
+/// A settings export (`settings.zip`) or plugin JAR; reads every live template file in it.
+///
+/// Those are the `.xml` entries directly inside a `templates` (settings exports) or
+/// `liveTemplates` (plugins) directory, in archive order.
+pub struct ArchiveSource(pub PathBuf);
+
+impl Source for ArchiveSource {
+    fn read(&mut self) -> Result<String> {
+        debug!("Reading {}", self.0.display());
+        let path = self.0.display().to_string();
+        let file = fs::File::open(&self.0).map_err(|e| T2eError::io(&path, e))?;
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| T2eError::io(&path, io::Error::from(e)))?;
+        let mut text = String::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(|e| T2eError::io(&path, io::Error::from(e)))?;
+            if !is_template_entry(entry.name()) {
+                continue;
+            }
+            let name = format!("{}!/{}", path, entry.name());
+            debug!("Reading {}", name);
+            let mut buf = Vec::new();
+            entry.read_to_end(&mut buf).map_err(|e| T2eError::io(&name, e))?;
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&decode(&name, buf)?);
+        }
+        if text.is_empty() {
+            return Err(T2eError::NoInput(format!("{}!/templates/*.xml", path)));
+        }
+        Ok(text)
+    }
+}
+
+fn is_template_entry(name: &str) -> bool {
+    let path = Path::new(name);
+    let dir = path.parent().and_then(Path::file_name);
+    path.extension().is_some_and(|e| e == "xml") && (dir == Some("templates".as_ref()) || dir == Some("liveTemplates".as_ref()))
+}
+
+/// Turns a command line input argument into sources.
+///
+/// `-` is stdin; anything else is expanded as a glob pattern, so a plain path simply matches
+/// itself. A pattern that matches nothing is an error. `.zip` and `.jar` files are read as
+/// archives.
+pub fn from_arg(input: &str) -> Result<Vec<Box<dyn Source>>> {
+    if input == "-" {
+        return Ok(vec![Box::new(StdinSource)]);
+    }
+    let paths = glob::glob(input)
+        .map_err(|e| T2eError::io(input, io::Error::new(io::ErrorKind::InvalidInput, e.msg)))?;
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    for path in paths {
+        let path = path.map_err(|e| T2eError::io(&e.path().display().to_string(), io::Error::from(e)))?;
+        if path.extension().is_some_and(|e| e == "zip" || e == "jar") {
+            sources.push(Box::new(ArchiveSource(path)));
+        } else {
+            sources.push(Box::new(FileSource(path)));
+        }
+    }
+    if sources.is_empty() {
+        return Err(T2eError::NoInput(input.to_string()));
+    }
+    Ok(sources)
+}
+
+/// The live template files of the installed IDE named `name`, e.g. `PyCharm` or `PyCharm2020.1`.
+pub fn from_ide(name: &str) -> Result<Vec<Box<dyn Source>>> {
+    let products = discover::find(&discover::products(&discover::Roots::from_env()), name);
+    if products.is_empty() {
+        return Err(T2eError::NoIde(name.to_string()));
+    }
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    for product in &products {
+        debug!("Using {}", product.config_dir.display());
+        sources.extend(product.templates().into_iter().map(|path| Box::new(FileSource(path)) as Box<dyn Source>));
+    }
+    if sources.is_empty() {
+        return Err(T2eError::NoInput(products[0].config_dir.join("templates").join("*.xml").display().to_string()));
+    }
+    Ok(sources)
+}
+
+/// Concatenates the contents of every source in order, making sure each one starts on a new line.
+pub fn read_all(sources: &mut [Box<dyn Source>]) -> Result<String> {
+    let mut text = String::new();
+    for source in sources {
+        let contents = source.read()?;
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&contents);
+    }
+    Ok(text)
+}
+
+fn decode(path: &str, buf: Vec<u8>) -> Result<String> {
+    String::from_utf8(buf).map_err(|e| T2eError::InvalidUtf8 { path: path.to_string(), offset: e.utf8_error().valid_up_to() })
+}
+
+fn clipboard_error(e: Box<dyn std::error::Error>) -> T2eError {
+    T2eError::Clipboard(e.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test1() {
+        let dir = env::temp_dir().join("t2e-source-test1");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "a\nb").unwrap();
+        fs::write(dir.join("b.txt"), "c\n").unwrap();
+        let pattern = dir.join("*.txt");
+        let mut sources = from_arg(pattern.to_str().unwrap()).unwrap();
+        let text = read_all(&mut sources).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test2() {
+        let pattern = env::temp_dir().join("t2e-source-test2-*.nothing");
+        match from_arg(pattern.to_str().unwrap()) {
+            Err(e) => assert_eq!(e.exit_code(), 2),
+            Ok(_) => panic!("pattern should not match"),
+        }
+    }
+
+    #[test]
+    fn test3() {
+        let path = env::temp_dir().join("t2e-source-test3.xml");
+        fs::write(&path, b"<template name=\"\xff\"/>").unwrap();
+        let result = FileSource(path.clone()).read();
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(T2eError::InvalidUtf8 { offset, .. }) => assert_eq!(offset, 16),
+            _ => panic!("expected invalid UTF-8"),
+        }
+    }
+
+    #[test]
+    fn test4() {
+        use std::io::Write;
+        let path = env::temp_dir().join("t2e-source-test4.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        for (name, contents) in &[
+            ("options/editor.xml", "<application/>"),
+            ("templates/shell.xml", "<templateSet group=\"shell\"/>"),
+            ("liveTemplates/Plugin.xml", "<templateSet group=\"plugin\"/>\n"),
+            ("templates/readme.txt", "not a template"),
+        ] {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        let mut sources = from_arg(path.to_str().unwrap()).unwrap();
+        let text = read_all(&mut sources).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(text, "<templateSet group=\"shell\"/>\n<templateSet group=\"plugin\"/>\n");
+    }
+}
@@ -0,0 +1,806 @@
+//! JetBrains live templates as read from `templates/*.xml` files.
+
+use quick_xml::{Reader, Writer};
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use regex::Regex;
+use std::io::{BufRead, Write};
+use crate::error::{Position, Result, T2eError};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Template {
+    pub name: String,
+    /// The template text, with `&#10;` and the other XML escapes decoded.
+    pub value: String,
+    pub description: String,
+    pub to_reformat: bool,
+    pub to_shorten_fq_names: bool,
+    pub variables: Vec<Variable>,
+    pub contexts: Vec<ContextOption>,
+    /// Attributes t2e doesn't interpret, such as `shortcut`, `deactivated` or `resource-bundle`,
+    /// as names and values in the order read; written back after the others.
+    pub other_attributes: Vec<(String, String)>,
+    /// `group` of the enclosing `<templateSet>`, empty outside of one.
+    pub group: String,
+    /// Byte offset of the `<template` start tag in the text it was read from.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Variable {
+    pub name: String,
+    pub expression: String,
+    pub default_value: String,
+    pub always_stop_at: bool,
+    /// Attributes t2e doesn't interpret, as for [`Template::other_attributes`].
+    pub other_attributes: Vec<(String, String)>,
+}
+
+/// An `<option name="..." value="..."/>` inside `<context>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextOption {
+    pub name: String,
+    pub value: bool,
+}
+
+/// The attributes of `<template>` that [`Template`] has fields for, in the order the IDE writes them.
+const TEMPLATE_ATTRIBUTES: &[&str] = &["name", "value", "description", "toReformat", "toShortenFQNames"];
+
+/// The attributes of `<variable>` that [`Variable`] has fields for.
+const VARIABLE_ATTRIBUTES: &[&str] = &["name", "expression", "defaultValue", "alwaysStopAt"];
+
+/// The variable marking where the caret ends up.
+pub const END: &str = "END";
+/// The variable holding the text selected when the template is inserted.
+pub const SELECTION: &str = "SELECTION";
+/// Variables the IDE provides itself, which need no `<variable>` element.
+pub const PREDEFINED: &[&str] = &[END, SELECTION];
+
+/// Contexts that the IDE enables along with their parent unless they are switched off.
+const CONTEXT_PARENTS: &[(&str, &str)] = &[
+    ("JAVA_STATEMENT", "JAVA_CODE"),
+    ("JAVA_EXPRESSION", "JAVA_CODE"),
+    ("JAVA_DECLARATION", "JAVA_CODE"),
+    ("JAVA_COMMENT", "JAVA_CODE"),
+    ("JAVA_STRING", "JAVA_CODE"),
+    ("COMPLETION", "JAVA_CODE"),
+    ("JAVA_CONSUMER", "JAVA_CODE"),
+    ("KOTLIN_STATEMENT", "KOTLIN"),
+    ("KOTLIN_EXPRESSION", "KOTLIN"),
+    ("KOTLIN_TOPLEVEL", "KOTLIN"),
+    ("KOTLIN_CLASS", "KOTLIN"),
+    ("KOTLIN_COMMENT", "KOTLIN"),
+    ("JS_STATEMENT", "JAVA_SCRIPT"),
+    ("JS_EXPRESSION", "JAVA_SCRIPT"),
+    ("JS_DOT_PROPERTY_ACCESS", "JAVA_SCRIPT"),
+    ("TS_STATEMENT", "TypeScript"),
+    ("TS_EXPRESSION", "TypeScript"),
+    ("CSS_PROPERTY_VALUE", "CSS"),
+    ("CSS_DECLARATION_BLOCK", "CSS"),
+    ("CSS_RULESET_LIST", "CSS"),
+    ("HTML_TEXT", "HTML"),
+    ("Python_Class", "Python"),
+    ("SQL_CODE", "SQL"),
+];
+
+impl Template {
+    /// Fills `{name}`, `{description}`, `{value}` and `{group}` in `format` with this template's fields.
+    /// `{{` and `}}` stand for literal braces; other placeholders are left alone.
+    pub fn format(&self, format: &str) -> String {
+        let mut result = String::new();
+        let mut rest = format;
+        while let Some(i) = rest.find(['{', '}']) {
+            result.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                result.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let end = rest.find('}').filter(|_| rest.starts_with('{'));
+            let field = end.and_then(|end| match &rest[1..end] {
+                "name" => Some(&self.name),
+                "description" => Some(&self.description),
+                "value" => Some(&self.value),
+                "group" => Some(&self.group),
+                _ => None,
+            });
+            match (field, end) {
+                (Some(field), Some(end)) => {
+                    result.push_str(field);
+                    rest = &rest[end + 1..];
+                }
+                _ => {
+                    result.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// The `<template>` element as the IDE writes it, indented by two spaces per level.
+    pub fn to_xml(&self) -> String {
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        // Writing to a Vec can't fail.
+        self.write(&mut writer).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    /// Writes the `<template>` element, with its variables and context options, to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut Writer<W>) -> quick_xml::Result<()> {
+        let mut start = BytesStart::borrowed_name(b"template");
+        push_attribute(&mut start, "name", &self.name);
+        push_attribute(&mut start, "value", &self.value);
+        push_attribute(&mut start, "description", &self.description);
+        push_attribute(&mut start, "toReformat", &self.to_reformat.to_string());
+        push_attribute(&mut start, "toShortenFQNames", &self.to_shorten_fq_names.to_string());
+        for (name, value) in &self.other_attributes {
+            push_attribute(&mut start, name, value);
+        }
+        writer.write_event(Event::Start(start))?;
+        for variable in &self.variables {
+            let mut tag = BytesStart::borrowed_name(b"variable");
+            push_attribute(&mut tag, "name", &variable.name);
+            push_attribute(&mut tag, "expression", &variable.expression);
+            push_attribute(&mut tag, "defaultValue", &variable.default_value);
+            push_attribute(&mut tag, "alwaysStopAt", &variable.always_stop_at.to_string());
+            for (name, value) in &variable.other_attributes {
+                push_attribute(&mut tag, name, value);
+            }
+            writer.write_event(Event::Empty(tag))?;
+        }
+        if self.contexts.is_empty() {
+            writer.write_event(Event::Empty(BytesStart::borrowed_name(b"context")))?;
+        } else {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"context")))?;
+            for option in &self.contexts {
+                let mut tag = BytesStart::borrowed_name(b"option");
+                push_attribute(&mut tag, "name", &option.name);
+                push_attribute(&mut tag, "value", &option.value.to_string());
+                writer.write_event(Event::Empty(tag))?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"context")))?;
+        }
+        writer.write_event(Event::End(BytesEnd::borrowed(b"template")))?;
+        Ok(())
+    }
+
+    /// The `$NAME$` references in the value, in order, including `$END$` and `$SELECTION$`.
+    /// `$$` is a literal dollar sign, as is a `$` that doesn't start a reference.
+    pub fn used_variables(&self) -> Vec<String> {
+        let mut used = Vec::new();
+        let mut rest = self.value.as_str();
+        while let Some(i) = rest.find('$') {
+            rest = &rest[i + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                rest = after;
+                continue;
+            }
+            let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            if len > 0 && rest[len..].starts_with('$') {
+                used.push(rest[..len].to_string());
+                rest = &rest[len + 1..];
+            }
+        }
+        used
+    }
+
+    /// Whether the template is available in `context`.
+    ///
+    /// An explicit option decides; without one a sub-context such as `JAVA_COMMENT` inherits
+    /// from its parent, so `JAVA_CODE` on and `JAVA_COMMENT` off excludes comments.
+    pub fn is_enabled_in(&self, context: &str) -> bool {
+        if let Some(option) = self.contexts.iter().find(|o| o.name == context) {
+            return option.value;
+        }
+        match CONTEXT_PARENTS.iter().find(|(child, _)| *child == context) {
+            Some((_, parent)) => self.is_enabled_in(parent),
+            None => false,
+        }
+    }
+
+    /// Whether some context is enabled for both templates, taking parent contexts into account.
+    pub fn shares_context_with(&self, other: &Template) -> bool {
+        self.contexts.iter().filter(|c| c.value).any(|c| other.is_enabled_in(&c.name))
+            || other.contexts.iter().filter(|c| c.value).any(|c| self.is_enabled_in(&c.name))
+    }
+}
+
+/// The language context that `context` belongs to, e.g. `JAVA_CODE` for `JAVA_STATEMENT`.
+pub fn language_context(context: &str) -> &str {
+    match CONTEXT_PARENTS.iter().find(|(child, _)| *child == context) {
+        Some((_, parent)) => language_context(parent),
+        None => context,
+    }
+}
+
+/// Which templates to keep.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Keep only templates enabled in at least one of these contexts; empty keeps all.
+    pub contexts: Vec<String>,
+    /// Drop templates enabled in any of these contexts.
+    pub exclude_contexts: Vec<String>,
+    /// Keep only templates from these template sets; empty keeps all.
+    pub groups: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, template: &Template) -> bool {
+        (self.contexts.is_empty() || self.contexts.iter().any(|c| template.is_enabled_in(c)))
+            && !self.exclude_contexts.iter().any(|c| template.is_enabled_in(c))
+            && (self.groups.is_empty() || self.groups.contains(&template.group))
+    }
+}
+
+/// Splits templates by group, keeping the order in which each group first appears.
+pub fn group_by_set(templates: Vec<Template>) -> Vec<(String, Vec<Template>)> {
+    let mut groups: Vec<(String, Vec<Template>)> = Vec::new();
+    for template in templates {
+        match groups.iter_mut().find(|(group, _)| *group == template.group) {
+            Some((_, members)) => members.push(template),
+            None => groups.push((template.group.clone(), vec![template])),
+        }
+    }
+    groups
+}
+
+/// A `<templateSet>` of `templates`, as the IDE writes a templates file.
+pub fn set_to_xml(group: &str, templates: &[Template]) -> String {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    let mut start = BytesStart::borrowed_name(b"templateSet");
+    push_attribute(&mut start, "group", group);
+    // Writing to a Vec can't fail.
+    writer.write_event(Event::Start(start)).unwrap();
+    for template in templates {
+        template.write(&mut writer).unwrap();
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(b"templateSet"))).unwrap();
+    let mut xml = String::from_utf8(writer.into_inner()).unwrap();
+    xml.push('\n');
+    xml
+}
+
+/// Rewrites a templates file in canonical form: one `<templateSet>` per group in order of
+/// appearance, templates sorted by name, context options sorted by name, attributes in the
+/// order the IDE writes them followed by the others as read, and line breaks as `&#10;`.
+///
+/// Malformed templates are an error rather than lost, as is anything the rewrite would drop:
+/// comments, other elements, text, or attributes of `<templateSet>`, `<context>` and `<option>`
+/// beyond the usual ones.
+pub fn canonicalize(xml: &str) -> Result<String> {
+    let builder = read_strict(xml)?;
+    if let Some(error) = builder.unrecognised.into_iter().next() {
+        return Err(error);
+    }
+    Ok(group_by_set(builder.templates).into_iter()
+        .map(|(group, mut templates)| {
+            sort_set(&mut templates);
+            set_to_xml(&group, &templates)
+        })
+        .collect())
+}
+
+/// Puts the templates of a set, and their context options, in the canonical order: by name.
+pub fn sort_set(templates: &mut [Template]) {
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    for template in templates {
+        template.contexts.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+/// A template the lenient reader could not read.
+#[derive(Debug)]
+pub struct Skipped {
+    /// Byte offset of the `<template` start tag.
+    pub offset: usize,
+    pub error: T2eError,
+}
+
+/// Reads every template in `xml`.
+///
+/// In strict mode the first XML error is returned. Otherwise every `<template>` is read on its
+/// own, from its start tag to its end tag or the next template, so unbalanced tags around it
+/// don't matter; templates that can't be read are returned next to the others.
+pub fn read(xml: &str, strict: bool) -> Result<(Vec<Template>, Vec<Skipped>)> {
+    if strict {
+        return Ok((read_strict(xml)?.templates, Vec::new()));
+    }
+
+    let start_tag = Regex::new(r"<template[\s/>]").unwrap();
+    // Neither commented-out templates nor text that looks like one count.
+    let hidden: Vec<(usize, usize)> = Regex::new(r"(?s)<!--.*?(?:-->|$)|<!\[CDATA\[.*?(?:\]\]>|$)").unwrap()
+        .find_iter(xml).map(|m| (m.start(), m.end())).collect();
+    let starts: Vec<usize> = start_tag.find_iter(xml).map(|m| m.start())
+        .filter(|start| !hidden.iter().any(|(from, to)| from < start && start < to))
+        .collect();
+    let mut templates = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).cloned().unwrap_or(xml.len());
+        let chunk = &xml[start..end];
+        let mut reader = Reader::from_str(chunk);
+        reader.trim_text(true);
+        reader.check_end_names(false);
+        let mut builder = Builder { group: enclosing_group(xml, start), ..Default::default() };
+        match builder.read_all(&mut reader, xml, start, true) {
+            Ok(()) if !builder.templates.is_empty() => templates.append(&mut builder.templates),
+            Ok(()) => skipped.push(Skipped {
+                offset: start,
+                error: T2eError::Xml {
+                    position: Position::of(xml, start),
+                    template: None,
+                    message: "Incomplete start tag".to_string(),
+                },
+            }),
+            Err(error) => skipped.push(Skipped { offset: start, error }),
+        }
+    }
+    Ok((templates, skipped))
+}
+
+fn read_strict(xml: &str) -> Result<Builder> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut builder = Builder::default();
+    builder.read_all(&mut reader, xml, 0, false)?;
+    Ok(builder)
+}
+
+/// The group of the `<templateSet>` still open at `offset`, for templates read on their own.
+fn enclosing_group(xml: &str, offset: usize) -> String {
+    let before = &xml[..offset];
+    let start = match before.rfind("<templateSet") {
+        Some(start) if !before[start..].contains("</templateSet") => start,
+        _ => return String::new(),
+    };
+    let mut reader = Reader::from_str(&xml[start..]);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    match reader.read_event(&mut buf) {
+        Ok(Event::Start(ref e)) => attribute(&reader, e, b"group").ok().flatten().unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Collects templates from reader events.
+#[derive(Default)]
+struct Builder {
+    templates: Vec<Template>,
+    current: Option<Template>,
+    in_context: bool,
+    group: String,
+    /// Whatever a rewrite from the templates alone would lose, as errors.
+    unrecognised: Vec<T2eError>,
+    /// When asked for, every element read so far; see [`outline`].
+    outline: Option<Vec<Element>>,
+    /// Indexes into `outline` of the elements still open.
+    open: Vec<usize>,
+}
+
+/// An element of an XML outline.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Element {
+    /// How many elements enclose this one.
+    pub depth: usize,
+    pub name: String,
+    /// The text directly inside the element, unescaped, CDATA included.
+    pub text: String,
+}
+
+/// Every element of `xml` with its depth and text, in document order, for reading other XML
+/// formats with the template reader.
+pub(crate) fn outline(xml: &str) -> Result<Vec<Element>> {
+    let mut reader = Reader::from_str(xml);
+    let mut builder = Builder { outline: Some(Vec::new()), ..Default::default() };
+    builder.read_all(&mut reader, xml, 0, false)?;
+    Ok(builder.outline.unwrap_or_default())
+}
+
+impl Builder {
+    /// Reads events until the end of the input, or, with `single`, the end of the first template.
+    /// `base` is the offset of the reader's input in `xml`.
+    fn read_all(&mut self, reader: &mut Reader<&[u8]>, xml: &str, base: usize, single: bool) -> Result<()> {
+        let mut buf = Vec::new();
+        loop {
+            let start = base + reader.buffer_position();
+            let event = reader.read_event(&mut buf);
+            match event {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    // The reader skips the whitespace before the tag.
+                    let offset = start + xml[start..].find('<').unwrap_or(0);
+                    let position = Position::of(xml, offset);
+                    // At the end of the input the reader hands out whatever it got as a start tag.
+                    if !xml[..base + reader.buffer_position()].ends_with('>') {
+                        return Err(T2eError::Xml {
+                            position,
+                            template: self.current.as_ref().map(|t| t.name.clone()),
+                            message: "Incomplete start tag".to_string(),
+                        });
+                    }
+                    let empty = matches!(event, Ok(Event::Empty(_)));
+                    self.start(reader, e, empty, offset, position)?;
+                    if let Some(outline) = &mut self.outline {
+                        outline.push(Element { depth: self.open.len(), name: String::from_utf8_lossy(e.name()).into_owned(), text: String::new() });
+                        if !empty {
+                            self.open.push(outline.len() - 1);
+                        }
+                    }
+                    if single && empty && self.current.is_none() && !self.templates.is_empty() {
+                        return Ok(());
+                    }
+                }
+                Ok(Event::End(ref e)) => {
+                    self.open.pop();
+                    match e.name() {
+                        b"template" => {
+                            if let Some(template) = self.current.take() {
+                                self.templates.push(template);
+                            }
+                            if single {
+                                return Ok(());
+                            }
+                        }
+                        b"context" => self.in_context = false,
+                        b"templateSet" => self.group.clear(),
+                        _ => (),
+                    }
+                }
+                Ok(Event::Eof) => {
+                    if let Some(template) = self.current.take() {
+                        // Reading templates one by one, this is a template cut off by the end of a
+                        // partial selection; otherwise the file is truncated.
+                        if !single {
+                            return Err(T2eError::Xml {
+                                position: Position::of(xml, template.offset),
+                                template: Some(template.name),
+                                message: "Unclosed <template> at the end of the input".to_string(),
+                            });
+                        }
+                        self.templates.push(template);
+                    }
+                    return Ok(());
+                }
+                Err(e) => return Err(self.xml_error(xml, base, reader, e)),
+                Ok(Event::Comment(_)) => self.unrecognised(Position::of(xml, start), "A comment"),
+                Ok(Event::Text(ref e)) if self.outline.is_some() => {
+                    let text = e.unescape_and_decode(reader).map_err(|e| self.xml_error(xml, base, reader, e))?;
+                    self.add_text(&text);
+                }
+                Ok(Event::CData(ref e)) if self.outline.is_some() => {
+                    let text = reader.decode(e).map_err(|e| self.xml_error(xml, base, reader, e))?;
+                    self.add_text(text);
+                }
+                Ok(Event::Text(_)) | Ok(Event::CData(_)) => self.unrecognised(Position::of(xml, start), "Text"),
+                Ok(Event::Decl(_)) | Ok(Event::PI(_)) | Ok(Event::DocType(_)) =>
+                    self.unrecognised(Position::of(xml, start), "A declaration"),
+            }
+            buf.clear();
+        }
+    }
+
+    fn xml_error(&self, xml: &str, base: usize, reader: &Reader<&[u8]>, e: quick_xml::Error) -> T2eError {
+        T2eError::Xml {
+            position: Position::of(xml, base + reader.buffer_position()),
+            template: self.current.as_ref().map(|t| t.name.clone()),
+            message: e.to_string(),
+        }
+    }
+
+    /// Adds text to the innermost open element of the outline.
+    fn add_text(&mut self, text: &str) {
+        if let (Some(outline), Some(&i)) = (&mut self.outline, self.open.last()) {
+            outline[i].text.push_str(text);
+        }
+    }
+
+    /// Notes something a rewrite would lose.
+    fn unrecognised(&mut self, position: Position, what: &str) {
+        self.unrecognised.push(T2eError::Xml {
+            position,
+            template: self.current.as_ref().map(|t| t.name.clone()),
+            message: format!("{} would be lost in rewriting", what),
+        });
+    }
+
+    /// Handles a start or empty tag found at `offset` in the whole text.
+    fn start(&mut self, reader: &Reader<&[u8]>, e: &BytesStart, empty: bool, offset: usize, position: Position) -> Result<()> {
+        let within = self.current.as_ref().map(|t| t.name.clone());
+        let xml_error = |e: quick_xml::Error| T2eError::Xml { position, template: within.clone(), message: e.to_string() };
+        let attr = |name: &[u8]| attribute(reader, e, name).map_err(xml_error);
+        let flag = |name: &[u8]| attr(name).map(|v| v.as_deref() == Some("true"));
+        // Attributes other than `known`, unescaped.
+        let others = |known: &[&str]| -> Result<Vec<(String, String)>> {
+            let mut others = Vec::new();
+            for a in e.attributes() {
+                let a = a.map_err(xml_error)?;
+                let name = String::from_utf8_lossy(a.key).into_owned();
+                if !known.contains(&name.as_str()) {
+                    others.push((name, a.unescape_and_decode_value(reader).map_err(xml_error)?));
+                }
+            }
+            Ok(others)
+        };
+        let element = String::from_utf8_lossy(e.name()).into_owned();
+        let known: &[&str] = match e.name() {
+            b"templateSet" => &["group"],
+            b"option" => &["name", "value"],
+            _ => &[],
+        };
+        if matches!(e.name(), b"templateSet" | b"context" | b"option") {
+            for (name, _) in others(known)? {
+                self.unrecognised(position, &format!("The attribute {} of <{}>", name, element));
+            }
+        }
+        match e.name() {
+            b"template" => {
+                let name = attr(b"name")?.ok_or_else(|| T2eError::MissingAttribute {
+                    position,
+                    element: "template".to_string(),
+                    attribute: "name".to_string(),
+                })?;
+                let template = Template {
+                    name,
+                    value: attr(b"value")?.unwrap_or_default(),
+                    description: attr(b"description")?.unwrap_or_default(),
+                    to_reformat: flag(b"toReformat")?,
+                    to_shorten_fq_names: flag(b"toShortenFQNames")?,
+                    variables: Vec::new(),
+                    contexts: Vec::new(),
+                    other_attributes: others(TEMPLATE_ATTRIBUTES)?,
+                    group: self.group.clone(),
+                    offset,
+                };
+                if empty {
+                    self.templates.push(template);
+                } else {
+                    self.current = Some(template);
+                }
+            }
+            b"variable" if self.current.is_some() => {
+                let variable = Variable {
+                    name: attr(b"name")?.unwrap_or_default(),
+                    expression: attr(b"expression")?.unwrap_or_default(),
+                    default_value: attr(b"defaultValue")?.unwrap_or_default(),
+                    always_stop_at: flag(b"alwaysStopAt")?,
+                    other_attributes: others(VARIABLE_ATTRIBUTES)?,
+                };
+                self.current.as_mut().unwrap().variables.push(variable);
+            }
+            b"templateSet" if !empty => self.group = attr(b"group")?.unwrap_or_default(),
+            b"templateSet" => (),
+            b"context" if self.current.is_some() => self.in_context = !empty,
+            b"option" if self.in_context && self.current.is_some() => {
+                let option = ContextOption {
+                    name: attr(b"name")?.unwrap_or_default(),
+                    value: flag(b"value")?,
+                };
+                self.current.as_mut().unwrap().contexts.push(option);
+            }
+            _ => self.unrecognised(position, &format!("The element <{}>", element)),
+        }
+        Ok(())
+    }
+}
+
+/// Adds an attribute escaped the way the IDE does it, with line breaks and tabs as character
+/// references so that they survive attribute value normalization.
+fn push_attribute(tag: &mut BytesStart, name: &str, value: &str) {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c => escaped.push(c),
+        }
+    }
+    tag.push_attribute((name.as_bytes(), escaped.as_bytes()));
+}
+
+/// Looks up an attribute of a start tag and unescapes its value.
+pub(crate) fn attribute<B: BufRead>(reader: &Reader<B>, e: &BytesStart, name: &[u8]) -> quick_xml::Result<Option<String>> {
+    for attr in e.attributes() {
+        let attr = attr?;
+        if attr.key == name {
+            return attr.unescape_and_decode_value(reader).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<templateSet group="mixed">
+  <template name="java" value="$X$&#10;$END$" description="d" toReformat="true" toShortenFQNames="true">
+    <variable name="X" expression="enum(&quot;a&quot;)" defaultValue="&quot;a&quot;" alwaysStopAt="true" />
+    <context>
+      <option name="JAVA_CODE" value="true" />
+      <option name="JAVA_COMMENT" value="false" />
+    </context>
+  </template>
+  <template name="sh" value="echo" description="" toReformat="false" toShortenFQNames="false">
+    <context>
+      <option name="SHELL_SCRIPT" value="true" />
+    </context>
+  </template>
+  <template name="both" value="" description="">
+    <context>
+      <option name="SHELL_SCRIPT" value="true" />
+      <option name="CSS" value="true" />
+    </context>
+  </template>
+  <template name="nowhere" value="" description="" />
+</templateSet>"#;
+
+    #[test]
+    fn test1() {
+        let (templates, skipped) = read(XML, true).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(templates.len(), 4);
+        let java = &templates[0];
+        assert_eq!(java.value, "$X$\n$END$");
+        assert!(java.to_reformat && java.to_shorten_fq_names);
+        assert_eq!(java.variables, vec![Variable {
+            name: "X".to_string(),
+            expression: "enum(\"a\")".to_string(),
+            default_value: "\"a\"".to_string(),
+            always_stop_at: true,
+            other_attributes: Vec::new(),
+        }]);
+        assert!(java.is_enabled_in("JAVA_CODE"));
+        assert!(java.is_enabled_in("JAVA_STATEMENT"));
+        assert!(!java.is_enabled_in("JAVA_COMMENT"));
+        assert!(!java.is_enabled_in("SHELL_SCRIPT"));
+        assert_eq!(templates[3].name, "nowhere");
+        let (lenient, _) = read(XML, false).unwrap();
+        assert_eq!(lenient, templates);
+    }
+
+    #[test]
+    fn test2() {
+        let (templates, _) = read(XML, true).unwrap();
+        let names = |filter: &Filter| templates.iter().filter(|t| filter.matches(t)).map(|t| t.name.as_str()).collect::<Vec<_>>();
+        let only = |contexts: &[&str], exclude: &[&str]| Filter {
+            contexts: contexts.iter().map(|c| c.to_string()).collect(),
+            exclude_contexts: exclude.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(names(&only(&[], &[])), vec!["java", "sh", "both", "nowhere"]);
+        assert_eq!(names(&only(&["SHELL_SCRIPT"], &[])), vec!["sh", "both"]);
+        assert_eq!(names(&only(&["SHELL_SCRIPT", "JAVA_STATEMENT"], &["CSS"])), vec!["java", "sh"]);
+        assert_eq!(names(&only(&["JAVA_COMMENT"], &[])), Vec::<&str>::new());
+        assert_eq!(names(&only(&[], &["SHELL_SCRIPT"])), vec!["java", "nowhere"]);
+    }
+
+    #[test]
+    fn test3() {
+        let template = Template { name: "n".to_string(), description: "d".to_string(), ..Default::default() };
+        assert_eq!(template.format("{name}: {description}{value}"), "n: d");
+        assert_eq!(template.format("{{name}} {other} {name"), "{name} {other} {name");
+    }
+
+    #[test]
+    fn test4() {
+        let xml = r#"<application>
+<templateSet group="shell">
+  <template name="a" value="" />
+  <template name="b" value=""><context /></template>
+</templateSet>
+<templateSet group="css">
+  <template name="c" value="" />
+</templateSet>
+<template name="loose" value="" />
+</application>"#;
+        for strict in &[true, false] {
+            let (templates, _) = read(xml, *strict).unwrap();
+            let groups: Vec<&str> = templates.iter().map(|t| t.group.as_str()).collect();
+            assert_eq!(groups, vec!["shell", "shell", "css", ""]);
+            let by_set = group_by_set(templates);
+            assert_eq!(by_set.iter().map(|(g, t)| (g.as_str(), t.len())).collect::<Vec<_>>(), vec![("shell", 2), ("css", 1), ("", 1)]);
+        }
+        let (templates, _) = read(xml, true).unwrap();
+        let filter = Filter { groups: vec!["css".to_string()], ..Default::default() };
+        assert_eq!(templates.iter().filter(|t| filter.matches(t)).map(|t| t.format("{group}/{name}")).collect::<Vec<_>>(), vec!["css/c"]);
+    }
+
+    #[test]
+    fn test5() {
+        let (templates, _) = read(XML, true).unwrap();
+        let xml = templates[0].to_xml();
+        assert_eq!(xml, r#"<template name="java" value="$X$&#10;$END$" description="d" toReformat="true" toShortenFQNames="true">
+  <variable name="X" expression="enum(&quot;a&quot;)" defaultValue="&quot;a&quot;" alwaysStopAt="true"/>
+  <context>
+    <option name="JAVA_CODE" value="true"/>
+    <option name="JAVA_COMMENT" value="false"/>
+  </context>
+</template>"#);
+        let (again, _) = read(&xml, true).unwrap();
+        assert_eq!(again[0], Template { group: String::new(), offset: 0, ..templates[0].clone() });
+    }
+
+    #[test]
+    fn test6() {
+        let template = Template { value: "$A$ costs $$5 $ not$ $B$$END$ $".to_string(), ..Default::default() };
+        assert_eq!(template.used_variables(), vec!["A", "B", "END"]);
+    }
+
+    #[test]
+    fn test7() {
+        let xml = "<templateSet group=\"g\">\n<template value=\"a&#xA;b\" name=\"z\"><context>\
+            <option value=\"true\" name=\"SHELL_SCRIPT\"/><option name=\"CSS\" value=\"false\"/></context></template>\n\
+            <template name=\"a\" value=\"\" description=\"\" toReformat=\"false\" toShortenFQNames=\"false\"/></templateSet>";
+        let canonical = canonicalize(xml).unwrap();
+        assert_eq!(canonical, r#"<templateSet group="g">
+  <template name="a" value="" description="" toReformat="false" toShortenFQNames="false">
+    <context/>
+  </template>
+  <template name="z" value="a&#10;b" description="" toReformat="false" toShortenFQNames="false">
+    <context>
+      <option name="CSS" value="false"/>
+      <option name="SHELL_SCRIPT" value="true"/>
+    </context>
+  </template>
+</templateSet>
+"#);
+        assert_eq!(canonicalize(&canonical).unwrap(), canonical);
+    }
+
+    #[test]
+    fn test8() {
+        let xml = r#"<templateSet group="g">
+  <template name="a" value="$X$" description="" toReformat="false" toShortenFQNames="false" shortcut="TAB" deactivated="true">
+    <variable name="X" expression="" defaultValue="" alwaysStopAt="true" key="x.name"/>
+    <context/>
+  </template>
+</templateSet>
+"#;
+        assert_eq!(canonicalize(xml).unwrap(), xml);
+        let (templates, _) = read(xml, true).unwrap();
+        assert_eq!(templates[0].other_attributes, vec![
+            ("shortcut".to_string(), "TAB".to_string()),
+            ("deactivated".to_string(), "true".to_string()),
+        ]);
+
+        let commented = xml.replace("<context/>", "<!-- keep --><context/>");
+        match canonicalize(&commented) {
+            Err(T2eError::Xml { template: Some(name), message, .. }) => {
+                assert_eq!(name, "a");
+                assert_eq!(message, "A comment would be lost in rewriting");
+            }
+            other => panic!("{:?}", other),
+        }
+        let wrapped = format!("<application>{}</application>", xml);
+        match canonicalize(&wrapped) {
+            Err(T2eError::Xml { message, .. }) => assert_eq!(message, "The element <application> would be lost in rewriting"),
+            other => panic!("{:?}", other),
+        }
+        let attributed = xml.replace("<templateSet group=\"g\">", "<templateSet group=\"g\" version=\"2\">");
+        match canonicalize(&attributed) {
+            Err(T2eError::Xml { message, .. }) => assert_eq!(message, "The attribute version of <templateSet> would be lost in rewriting"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test9() {
+        let xml = "<templateSet group=\"g\">\n  <template name=\"a\" value=\"x\" description=\"\">\n";
+        match read(xml, true) {
+            Err(T2eError::Xml { position, template: Some(name), message }) => {
+                assert_eq!((position.line, name.as_str()), (2, "a"));
+                assert_eq!(message, "Unclosed <template> at the end of the input");
+            }
+            other => panic!("{:?}", other),
+        }
+        let (templates, _) = read(xml, false).unwrap();
+        assert_eq!(templates.len(), 1);
+    }
+}
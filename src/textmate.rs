@@ -0,0 +1,359 @@
+//! Sublime Text `.sublime-snippet` and TextMate `.tmSnippet` files.
+//!
+//! Both keep one snippet per file and write bodies in the TextMate snippet grammar, which VS Code
+//! adopted, so reading goes through [`VsSnippet`]. Sublime Text has no choices, so an `enum()`
+//! becomes a field holding its first option.
+//!
+//! See <https://docs.sublimetext.io/guide/extensibility/snippets.html> and
+//! <https://macromates.com/manual/en/snippets>.
+
+use log::warn;
+use quick_xml::Reader;
+use quick_xml::events::{BytesText, Event};
+use std::path::PathBuf;
+use crate::emit::FilesEmitter;
+use crate::error::Result;
+use crate::expr::Expr;
+use crate::fields;
+use crate::template::{self, Template};
+use crate::vscode::{self, VsSnippet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Sublime,
+    TextMate,
+}
+
+impl Format {
+    /// The template group of converted snippets.
+    pub fn group(self) -> &'static str {
+        match self {
+            Format::Sublime => "Sublime Text",
+            Format::TextMate => "TextMate",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Sublime => "sublime-snippet",
+            Format::TextMate => "tmSnippet",
+        }
+    }
+}
+
+/// Template contexts and the scope selectors of the same languages; a more specific selector comes
+/// before the one it extends.
+const SELECTORS: &[(&str, &str)] = &[
+    ("Markdown", "text.html.markdown"),
+    ("JAVA_CODE", "source.java"),
+    ("KOTLIN", "source.kotlin"),
+    ("JAVA_SCRIPT", "source.js"),
+    ("TypeScript", "source.ts"),
+    ("Python", "source.python"),
+    ("SHELL_SCRIPT", "source.shell"),
+    ("CSS", "source.css"),
+    ("HTML", "text.html"),
+    ("SQL", "source.sql"),
+    ("GO", "source.go"),
+    ("RUST_FILE", "source.rust"),
+];
+
+/// Template expressions and the environment variables both editors set to the same value.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("fileName()", "TM_FILENAME"),
+    ("filePath()", "TM_FILEPATH"),
+    ("lineNumber()", "TM_LINE_NUMBER"),
+];
+
+/// Whether any top-level element of `text` is a `<snippet>` or `<plist>`. Several files read
+/// together may mix these with template XML.
+pub fn is_snippet(text: &str) -> bool {
+    top_level(text).iter().any(|name| is_snippet_element(name))
+}
+
+/// Whether any top-level element of `text` is something other than a snippet, such as a
+/// `<templateSet>`.
+pub fn has_templates(text: &str) -> bool {
+    top_level(text).iter().any(|name| !is_snippet_element(name))
+}
+
+fn is_snippet_element(name: &[u8]) -> bool {
+    name == b"snippet" || name == b"plist"
+}
+
+/// The names of the top-level elements, as far as `text` is well-formed.
+fn top_level(text: &str) -> Vec<Vec<u8>> {
+    let mut reader = Reader::from_str(text);
+    let mut buf = Vec::new();
+    let mut names = Vec::new();
+    let mut depth = 0;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                if depth == 0 {
+                    names.push(e.name().to_vec());
+                }
+                depth += 1;
+            }
+            Ok(Event::Empty(ref e)) if depth == 0 => names.push(e.name().to_vec()),
+            Ok(Event::End(_)) => depth -= 1,
+            Ok(Event::Eof) | Err(_) => return names,
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
+/// Reads every `<snippet>` and `<plist>` in `text`, which may be several files one after another.
+///
+/// The VS Code language of each snippet comes from the first selector of its scope that names a
+/// known language; other selectors are kept as they are.
+pub fn read(text: &str) -> Result<Vec<(Format, VsSnippet)>> {
+    let mut snippets = Vec::new();
+    let elements = template::outline(text)?;
+    for (i, element) in elements.iter().enumerate() {
+        let name = element.name.as_str();
+        if element.depth != 0 || (name != "snippet" && name != "plist") {
+            continue;
+        }
+        let end = elements[i + 1..].iter().position(|e| e.depth == 0).map_or(elements.len(), |n| i + 1 + n);
+        let inner = &elements[i + 1..end];
+        let get = |key: &str| -> String {
+            if name == "snippet" {
+                inner.iter().find(|e| e.depth == 1 && e.name == key).map(|e| e.text.clone())
+            } else {
+                // <plist><dict><key>K</key><string>V</string>...
+                inner.windows(2)
+                    .find(|pair| pair[0].depth == 2 && pair[0].name == "key" && pair[0].text == key && pair[1].name == "string")
+                    .map(|pair| pair[1].text.clone())
+            }.unwrap_or_default()
+        };
+        let format = if name == "snippet" { Format::Sublime } else { Format::TextMate };
+        let trigger = get("tabTrigger");
+        let description = get(if format == Format::Sublime { "description" } else { "name" });
+        snippets.push((format, VsSnippet {
+            name: if description.is_empty() { trigger.clone() } else { description.clone() },
+            prefixes: if trigger.is_empty() { Vec::new() } else { vec![trigger] },
+            body: get("content").trim_matches(&['\n', '\r'][..]).to_string(),
+            description,
+            scopes: get("scope").split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).map(language).collect(),
+        }));
+    }
+    Ok(snippets)
+}
+
+/// The VS Code language of the first selector in `scope`, e.g. `source.shell.bash - comment`.
+fn language(scope: &str) -> String {
+    let selector = scope.split_whitespace().next().unwrap_or_default();
+    let context = SELECTORS.iter()
+        .find(|(_, s)| selector == *s || selector.starts_with(&format!("{}.", s)))
+        .map(|(context, _)| *context);
+    match context.and_then(|c| vscode::SCOPES.iter().find(|(_, context)| *context == c)) {
+        Some((language, _)) => language.to_string(),
+        None => scope.to_string(),
+    }
+}
+
+/// The equivalent template in the format's group, and notes on whatever couldn't be translated.
+pub fn to_template(format: Format, snippet: &VsSnippet) -> (Template, Vec<String>) {
+    let (template, notes) = snippet.to_template();
+    (Template { group: format.group().to_string(), ..template }, notes)
+}
+
+/// A snippet file for `template`, and notes on whatever couldn't be translated.
+pub fn from_template(format: Format, template: &Template) -> (String, Vec<String>) {
+    let (body, mut notes) = fields::render(template, &Syntax(format));
+
+    let mut scopes: Vec<&str> = Vec::new();
+    for context in template.contexts.iter().filter(|c| c.value && c.name != "OTHER") {
+        match SELECTORS.iter().find(|(c, _)| *c == template::language_context(&context.name)) {
+            Some((_, selector)) if !scopes.contains(selector) => scopes.push(selector),
+            Some(_) => (),
+            None => notes.push(format!("No scope for context {}", context.name)),
+        }
+    }
+    if template.to_reformat {
+        notes.push(format!("toReformat has no {} equivalent", format.group()));
+    }
+
+    let description = if template.description.is_empty() { &template.name } else { &template.description };
+    let file = match format {
+        Format::Sublime => {
+            let mut file = String::from("<snippet>\n");
+            // A CDATA section can't contain its own end, so that is split over two sections.
+            file.push_str(&format!("  <content><![CDATA[{}]]></content>\n", body.replace("]]>", "]]]]><![CDATA[>")));
+            file.push_str(&format!("  <tabTrigger>{}</tabTrigger>\n", xml_text(&template.name)));
+            if !scopes.is_empty() {
+                file.push_str(&format!("  <scope>{}</scope>\n", xml_text(&scopes.join(", "))));
+            }
+            file.push_str(&format!("  <description>{}</description>\n</snippet>\n", xml_text(description)));
+            file
+        }
+        Format::TextMate => {
+            let mut file = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                <plist version=\"1.0\">\n<dict>\n");
+            let mut entry = |key: &str, value: &str| file.push_str(&format!("\t<key>{}</key>\n\t<string>{}</string>\n", key, xml_text(value)));
+            entry("content", &body);
+            entry("name", description);
+            if !scopes.is_empty() {
+                entry("scope", &scopes.join(", "));
+            }
+            entry("tabTrigger", &template.name);
+            entry("uuid", &uuid(template));
+            file.push_str("</dict>\n</plist>\n");
+            file
+        }
+    };
+    (file, notes)
+}
+
+/// A UUID that TextMate requires of every snippet, the same for the same group and name across
+/// runs and builds: two 64-bit FNV-1a hashes of the group and name, salted with 0 and 1.
+fn uuid(template: &Template) -> String {
+    let hash = |salt: u8| {
+        let bytes = std::iter::once(&salt).chain(template.group.as_bytes()).chain(&[0]).chain(template.name.as_bytes());
+        bytes.fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3))
+    };
+    let (a, b) = (hash(0), hash(1));
+    format!("{:08X}-{:04X}-4{:03X}-{:04X}-{:012X}", a >> 32, (a >> 16) & 0xFFFF, a & 0xFFF,
+        0x8000 | ((b >> 48) & 0x3FFF), b & 0xFFFF_FFFF_FFFF)
+}
+
+struct Syntax(Format);
+
+impl Syntax {
+    /// Characters with a meaning in snippet text; Sublime Text has no interpolated code.
+    fn special(&self) -> &'static [char] {
+        if self.0 == Format::TextMate { &['\\', '$', '`'] } else { &['\\', '$'] }
+    }
+}
+
+impl fields::Syntax for Syntax {
+    fn text(&self, text: &str) -> String {
+        fields::escape(text, self.special())
+    }
+
+    fn placeholder(&self, number: usize, default: &str) -> String {
+        format!("${{{}:{}}}", number, fields::escape(default, &[self.special(), &['}']].concat()))
+    }
+
+    fn choice(&self, number: usize, choices: &[String], notes: &mut Vec<String>) -> String {
+        if self.0 == Format::Sublime {
+            notes.push(format!("Sublime Text has no choices; field {} starts as {}", number, choices[0]));
+            return self.placeholder(number, &choices[0]);
+        }
+        let choices: Vec<String> = choices.iter().map(|c| fields::escape(c, &[self.special(), &['}', ',', '|']].concat())).collect();
+        format!("${{{}|{}|}}", number, choices.join(","))
+    }
+
+    fn computed(&self, expression: &Expr) -> Option<String> {
+        fields::lookup(FUNCTIONS, expression).map(|name| format!("${}", name))
+    }
+
+    fn selection(&self) -> String {
+        "$TM_SELECTED_TEXT".to_string()
+    }
+}
+
+fn xml_text(text: &str) -> String {
+    String::from_utf8_lossy(BytesText::from_plain_str(text).escaped()).into_owned()
+}
+
+/// A `NAME.sublime-snippet` or `NAME.tmSnippet` file for every template.
+pub struct Snippets {
+    pub format: Format,
+}
+
+impl FilesEmitter for Snippets {
+    fn emit_files(&self, templates: &[Template]) -> Vec<(PathBuf, String)> {
+        let refs: Vec<&Template> = templates.iter().collect();
+        fields::file_names(&refs).into_iter().zip(templates)
+            .map(|(name, t)| {
+                let (file, notes) = from_template(self.format, t);
+                for note in notes {
+                    warn!("Template \"{}\": {}", t.name, note);
+                }
+                (PathBuf::from(format!("{}.{}", name, self.format.extension())), file)
+            })
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::{Extractor, TemplateFields};
+
+    const XML: &str = r#"<templateSet group="sh">
+  <template name="for" value="for $X$ in `$F$`; do&#10;  $SELECTION$$END$ $$HOME ]]&gt;&#10;done" description="Loop &amp; more" toReformat="true">
+    <variable name="X" expression="enum(&quot;a,b&quot;, &quot;c&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="F" expression="fileName()" defaultValue="" alwaysStopAt="false" />
+    <context>
+      <option name="SHELL_SCRIPT" value="true" />
+      <option name="JAVA_STATEMENT" value="true" />
+      <option name="NOWHERE" value="true" />
+    </context>
+  </template>
+</templateSet>"#;
+
+    #[test]
+    fn test1() {
+        let (templates, _) = template::read(XML, true).unwrap();
+        let (file, notes) = from_template(Format::Sublime, &templates[0]);
+        assert_eq!(file, r#"<snippet>
+  <content><![CDATA[for ${1:a,b} in `$TM_FILENAME`; do
+  $TM_SELECTED_TEXT$0 \$HOME ]]]]><![CDATA[>
+done]]></content>
+  <tabTrigger>for</tabTrigger>
+  <scope>source.shell, source.java</scope>
+  <description>Loop &amp; more</description>
+</snippet>
+"#);
+        assert_eq!(notes, vec![
+            "Sublime Text has no choices; field 1 starts as a,b",
+            "No scope for context NOWHERE",
+            "toReformat has no Sublime Text equivalent",
+        ]);
+
+        let read = read(&file).unwrap();
+        assert_eq!(read.len(), 1);
+        let (back, _) = to_template(read[0].0, &read[0].1);
+        assert_eq!(back.group, "Sublime Text");
+        assert_eq!(back.name, "for");
+        assert_eq!(back.description, "Loop & more");
+        assert_eq!(back.value, "for $VAR1$ in `$TM_FILENAME$`; do\n  $SELECTION$$END$ $$HOME ]]>\ndone");
+        let contexts: Vec<&str> = back.contexts.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(contexts, vec!["SHELL_SCRIPT", "JAVA_CODE"]);
+    }
+
+    #[test]
+    fn test2() {
+        let (templates, _) = template::read(XML, true).unwrap();
+        let (file, _) = from_template(Format::TextMate, &templates[0]);
+        assert!(file.contains("\t<key>content</key>\n\t<string>for ${1|a\\,b,c|} in \\`$TM_FILENAME\\`; do\n  $TM_SELECTED_TEXT$0 \\$HOME ]]&gt;\ndone</string>\n"));
+        assert!(file.contains("\t<key>uuid</key>\n\t<string>848581F5-5E0A-4803-9812-B16C3E064738</string>"));
+        assert!(is_snippet(&file) && !is_snippet(XML));
+
+        let sublime = "<snippet><content><![CDATA[\nx $1\n]]></content><tabTrigger>t</tabTrigger><scope>source.python - comment, source.foo</scope></snippet>\n";
+        let read = read(&format!("{}{}", file, sublime)).unwrap();
+        let (back, notes) = to_template(read[0].0, &read[0].1);
+        assert_eq!(back.group, "TextMate");
+        assert_eq!(back.variables[0].expression, "enum(\"a,b\", \"c\")");
+        assert!(notes.is_empty());
+        assert_eq!(read[1].0, Format::Sublime);
+        assert_eq!(read[1].1.body, "x $1");
+        assert_eq!(read[1].1.scopes, vec!["python", "source.foo"]);
+    }
+
+    #[test]
+    fn test3() {
+        let sublime = "<snippet><content><![CDATA[x <template name=\"no\"/>]]></content><tabTrigger>t</tabTrigger></snippet>\n";
+        let text = format!("{}{}\n{}", XML, sublime, XML.replace("group=\"sh\"", "group=\"other\""));
+        assert!(is_snippet(&text) && has_templates(&text));
+        assert!(!has_templates(sublime) && !is_snippet(XML));
+        let extractor = TemplateFields { field: "{group}/{name}".to_string(), ..Default::default() };
+        assert_eq!(extractor.extract(&text).unwrap(), vec!["Sublime Text/t", "sh/for", "other/for"]);
+    }
+}
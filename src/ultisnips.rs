@@ -0,0 +1,154 @@
+//! Vim UltiSnips `.snippets` files, one per filetype.
+//!
+//! See <https://github.com/SirVer/ultisnips/blob/master/doc/UltiSnips.txt>.
+
+use log::warn;
+use std::path::PathBuf;
+use crate::emit::FilesEmitter;
+use crate::escape;
+use crate::expr::Expr;
+use crate::fields;
+use crate::template::Template;
+
+/// Template contexts and the Vim filetypes of the same languages.
+const FILETYPES: &[(&str, &str)] = &[
+    ("JAVA_CODE", "java"),
+    ("KOTLIN", "kotlin"),
+    ("JAVA_SCRIPT", "javascript"),
+    ("TypeScript", "typescript"),
+    ("Python", "python"),
+    ("SHELL_SCRIPT", "sh"),
+    ("CSS", "css"),
+    ("HTML", "html"),
+    ("SQL", "sql"),
+    ("GO", "go"),
+    ("RUST_FILE", "rust"),
+    ("Markdown", "markdown"),
+    ("OTHER", "all"),
+];
+
+/// Template expressions and the Vim script interpolations computing the same value.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("fileName()", "expand('%:t')"),
+    ("fileNameWithoutExtension()", "expand('%:t:r')"),
+    ("filePath()", "expand('%:p')"),
+    ("lineNumber()", "line('.')"),
+    ("clipboard()", "@+"),
+    ("user()", "$USER"),
+    ("date(\"yyyy\")", "strftime('%Y')"),
+    ("date(\"MM\")", "strftime('%m')"),
+    ("date(\"dd\")", "strftime('%d')"),
+    ("date(\"yyyy-MM-dd\")", "strftime('%Y-%m-%d')"),
+    ("time(\"HH\")", "strftime('%H')"),
+    ("time(\"mm\")", "strftime('%M')"),
+    ("time(\"ss\")", "strftime('%S')"),
+];
+
+/// A `FILETYPE.snippets` file for every filetype the templates are enabled in; templates enabled
+/// nowhere known go to `all.snippets`.
+pub struct UltiSnips;
+
+impl FilesEmitter for UltiSnips {
+    fn emit_files(&self, templates: &[Template]) -> Vec<(PathBuf, String)> {
+        fields::by_language(templates, FILETYPES, "all").into_iter()
+            .map(|(filetype, templates)| {
+                let snippets: Vec<String> = templates.into_iter().map(snippet).collect();
+                (PathBuf::from(format!("{}.snippets", filetype)), snippets.join("\n"))
+            })
+            .collect()
+    }
+}
+
+/// A `snippet ... endsnippet` block, described by the template's description or else its name.
+///
+/// UltiSnips has no escape for a quote in a description, which ends at the last quote but one on
+/// the line, so such a description is left out rather than changed.
+pub fn snippet(template: &Template) -> String {
+    let (body, mut notes) = fields::render(template, &Syntax);
+    let description = if template.description.is_empty() { &template.name } else { &template.description };
+    let header = if description.contains(['"', '\n', '\r']) {
+        notes.push(format!("The description {} can't be written in UltiSnips and is left out", escape::quote(description)));
+        format!("snippet {}", trigger(&template.name))
+    } else {
+        format!("snippet {} \"{}\"", trigger(&template.name), description)
+    };
+    for note in notes {
+        warn!("Template \"{}\": {}", template.name, note);
+    }
+    format!("{}\n{}\nendsnippet\n", header, body)
+}
+
+struct Syntax;
+
+impl fields::Syntax for Syntax {
+    fn text(&self, text: &str) -> String {
+        fields::escape(text, &['\\', '`', '$'])
+    }
+
+    fn placeholder(&self, number: usize, default: &str) -> String {
+        format!("${{{}:{}}}", number, fields::escape(default, &['\\', '`', '$', '}']))
+    }
+
+    fn choice(&self, number: usize, choices: &[String], _: &mut Vec<String>) -> String {
+        let choices: Vec<String> = choices.iter().map(|c| fields::escape(c, &['\\', '`', '$', '}', ',', '|'])).collect();
+        format!("${{{}|{}|}}", number, choices.join(","))
+    }
+
+    fn computed(&self, expression: &Expr) -> Option<String> {
+        fields::lookup(FUNCTIONS, expression).map(|f| format!("`!v {}`", f))
+    }
+
+    fn selection(&self) -> String {
+        "${VISUAL}".to_string()
+    }
+}
+
+/// The trigger, between delimiters when it contains white space.
+fn trigger(name: &str) -> String {
+    if !name.contains(char::is_whitespace) {
+        return name.to_string();
+    }
+    let delimiter = ['"', '!', '|', '%', '#'].iter().find(|d| !name.contains(**d)).unwrap_or(&'"');
+    format!("{}{}{}", delimiter, name, delimiter)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+
+    const XML: &str = r#"<templateSet group="sh">
+  <template name="for" value="for $X$ in `$F$`; do&#10;  $SELECTION$$END$ $$HOME&#10;done # $X$" description="Loop &quot;x&quot;">
+    <variable name="X" expression="enum(&quot;a,b&quot;, &quot;c&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="F" expression="fileName()" defaultValue="" alwaysStopAt="false" />
+    <context>
+      <option name="SHELL_SCRIPT" value="true" />
+      <option name="OTHER" value="true" />
+    </context>
+  </template>
+  <template name="a b" value="$A$" description="">
+    <variable name="A" expression="" defaultValue="&quot;x}&quot;" alwaysStopAt="true" />
+    <context>
+      <option name="JAVA_STATEMENT" value="true" />
+      <option name="NOWHERE" value="true" />
+    </context>
+  </template>
+</templateSet>"#;
+
+    #[test]
+    fn test1() {
+        let (templates, _) = template::read(XML, true).unwrap();
+        let files = UltiSnips.emit_files(&templates);
+        let paths: Vec<&str> = files.iter().map(|(p, _)| p.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["all.snippets", "java.snippets", "sh.snippets"]);
+        assert_eq!(files[2].1, r#"snippet for
+for ${1|a\,b,c|} in \``!v expand('%:t')`\`; do
+  ${VISUAL}$0 \$HOME
+done # $1
+endsnippet
+"#);
+        assert_eq!(files[1].1, "snippet \"a b\" \"a b\"\n${1:x\\}}\nendsnippet\n");
+        assert_eq!(files[0].1, files[2].1);
+    }
+}
@@ -0,0 +1,728 @@
+//! VS Code snippets: choice placeholders, `.code-snippets` files and their conversion to
+//! JetBrains templates.
+//!
+//! See <https://code.visualstudio.com/docs/editor/userdefinedsnippets#_grammar>.
+
+use serde_json::{json, Map, Value};
+use log::warn;
+use crate::emit::{Emitter, JetBrainsEnum, TemplateEmitter};
+use crate::error::{Position, Result, T2eError};
+use crate::escape;
+use crate::expr::Expr;
+use crate::fields;
+use crate::template::{ContextOption, Template, Variable};
+
+/// The template group of converted snippets.
+pub const GROUP: &str = "VS Code";
+
+/// VS Code language identifiers and the template contexts of the same languages.
+pub const SCOPES: &[(&str, &str)] = &[
+    ("java", "JAVA_CODE"),
+    ("kotlin", "KOTLIN"),
+    ("javascript", "JAVA_SCRIPT"),
+    ("javascriptreact", "JAVA_SCRIPT"),
+    ("typescript", "TypeScript"),
+    ("typescriptreact", "TypeScript"),
+    ("python", "Python"),
+    ("shellscript", "SHELL_SCRIPT"),
+    ("css", "CSS"),
+    ("html", "HTML"),
+    ("sql", "SQL"),
+    ("go", "GO"),
+    ("rust", "RUST_FILE"),
+    ("markdown", "Markdown"),
+    ("plaintext", "OTHER"),
+];
+
+/// Template expressions and the VS Code variables holding the same value.
+/// `TM_SELECTED_TEXT` becomes `$SELECTION$` instead.
+const VARIABLES: &[(&str, &str)] = &[
+    ("fileName()", "TM_FILENAME"),
+    ("fileNameWithoutExtension()", "TM_FILENAME_BASE"),
+    ("filePath()", "TM_FILEPATH"),
+    ("fileRelativePath()", "RELATIVE_FILEPATH"),
+    ("lineNumber()", "TM_LINE_NUMBER"),
+    ("clipboard()", "CLIPBOARD"),
+    ("date(\"yyyy\")", "CURRENT_YEAR"),
+    ("date(\"yy\")", "CURRENT_YEAR_SHORT"),
+    ("date(\"MM\")", "CURRENT_MONTH"),
+    ("date(\"MMMM\")", "CURRENT_MONTH_NAME"),
+    ("date(\"MMM\")", "CURRENT_MONTH_NAME_SHORT"),
+    ("date(\"dd\")", "CURRENT_DATE"),
+    ("date(\"EEEE\")", "CURRENT_DAY_NAME"),
+    ("date(\"EEE\")", "CURRENT_DAY_NAME_SHORT"),
+    ("time(\"HH\")", "CURRENT_HOUR"),
+    ("time(\"mm\")", "CURRENT_MINUTE"),
+    ("time(\"ss\")", "CURRENT_SECOND"),
+];
+
+/// A choice placeholder, `${1|a,b,c|}`.
+pub struct Choice {
+    pub tab_stop: usize,
+}
+
+impl Default for Choice {
+    fn default() -> Choice {
+        Choice { tab_stop: 1 }
+    }
+}
+
+impl Emitter for Choice {
+    fn emit(&self, items: &[String]) -> String {
+        let items: Vec<String> = items.iter().map(|item| escape_choice(item)).collect();
+        format!("${{{}|{}|}}", self.tab_stop, items.join(","))
+    }
+}
+
+/// A `.code-snippets` file with one snippet whose body is a [`Choice`].
+pub struct Snippet {
+    /// The key of the snippet, shown in the snippet picker.
+    pub name: String,
+    pub prefix: String,
+    pub description: String,
+    pub choice: Choice,
+}
+
+impl Emitter for Snippet {
+    fn emit(&self, items: &[String]) -> String {
+        let mut file = Map::new();
+        file.insert(self.name.clone(), entry(&self.prefix, &self.choice.emit(items), &self.description));
+        format!("{:#}", Value::Object(file))
+    }
+}
+
+/// One snippet of a `.code-snippets` file; the body is split into lines as VS Code writes it.
+pub fn entry(prefix: &str, body: &str, description: &str) -> Value {
+    let mut entry = json!({
+        "prefix": prefix,
+        "body": body.split('\n').collect::<Vec<_>>(),
+    });
+    if !description.is_empty() {
+        entry["description"] = description.into();
+    }
+    entry
+}
+
+/// A snippet read from a VS Code snippets file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VsSnippet {
+    /// The key of the snippet in the file.
+    pub name: String,
+    pub prefixes: Vec<String>,
+    /// The body lines joined with line breaks.
+    pub body: String,
+    pub description: String,
+    /// Language identifiers from `scope`; empty means every language.
+    pub scopes: Vec<String>,
+}
+
+/// Whether `text` looks like a snippets file rather than template XML.
+pub fn is_snippets(text: &str) -> bool {
+    strip_comments(text).trim_start().starts_with('{')
+}
+
+/// Reads a `.code-snippets` file or a language snippets file, which may contain comments and
+/// trailing commas. Entries without a `body` are skipped.
+pub fn read(text: &str) -> Result<Vec<VsSnippet>> {
+    let json: Value = serde_json::from_str(&strip_comments(text)).map_err(|e| {
+        let message = e.to_string();
+        // Both count from 1; the column counts bytes.
+        let line_start: usize = text.split_inclusive('\n').take(e.line().saturating_sub(1)).map(str::len).sum();
+        T2eError::Json {
+            position: Position::of(text, line_start + e.column().saturating_sub(1)),
+            message: message.split(" at line ").next().unwrap_or(&message).to_string(),
+        }
+    })?;
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        match value {
+            Some(Value::String(s)) => vec![s.clone()],
+            Some(Value::Array(items)) => items.iter().filter_map(|i| i.as_str().map(String::from)).collect(),
+            _ => Vec::new(),
+        }
+    };
+    let entries = match json {
+        Value::Object(entries) => entries,
+        _ => return Ok(Vec::new()),
+    };
+    Ok(entries.into_iter()
+        .filter(|(_, entry)| entry.get("body").is_some())
+        .map(|(name, entry)| VsSnippet {
+            prefixes: strings(entry.get("prefix")),
+            body: strings(entry.get("body")).join("\n"),
+            description: entry.get("description").and_then(Value::as_str).unwrap_or_default().to_string(),
+            scopes: entry.get("scope").and_then(Value::as_str).unwrap_or_default()
+                .split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect(),
+            name,
+        })
+        .collect())
+}
+
+/// Blanks out `//` and `/* */` comments and trailing commas, keeping every line and column
+/// where it was so that errors point into the original text.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    let blank = |c: char| if c == '\n' { '\n' } else { ' ' };
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                out.push(' ');
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    out.push(' ');
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                out.push(' ');
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    out.push(blank(c));
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            ('}', _) | (']', _) => {
+                let kept = out.trim_end().len();
+                if out[..kept].ends_with(',') {
+                    out.replace_range(kept - 1..kept, " ");
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Part of a snippet body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Text(String),
+    /// `$1`, `${1:default}` or `${1|a,b|}`; 0 is the final caret position.
+    TabStop { index: usize, default: Vec<Part>, choices: Option<Vec<String>>, transform: bool },
+    /// `$NAME` or `${NAME:default}`.
+    Variable { name: String, default: Vec<Part>, transform: bool },
+}
+
+/// Splits a snippet body into text and placeholders. Anything that doesn't parse as a
+/// placeholder is text, as in VS Code.
+pub fn parse_body(body: &str) -> Vec<Part> {
+    let mut parser = BodyParser { rest: body };
+    parser.parts(false)
+}
+
+struct BodyParser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> BodyParser<'a> {
+    /// Parses until the end or, `nested` in a placeholder default, an unescaped `}`.
+    fn parts(&mut self, nested: bool) -> Vec<Part> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        while let Some(c) = self.rest.chars().next() {
+            if nested && c == '}' {
+                break;
+            }
+            if c == '\\' && self.rest[1..].starts_with(['$', '}', '\\']) {
+                text.push_str(&self.rest[1..2]);
+                self.rest = &self.rest[2..];
+                continue;
+            }
+            if c == '$' {
+                let saved = self.rest;
+                self.rest = &self.rest[1..];
+                if let Some(part) = self.placeholder() {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(part);
+                    continue;
+                }
+                self.rest = &saved[1..];
+            } else {
+                self.rest = &self.rest[c.len_utf8()..];
+            }
+            text.push(c);
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        parts
+    }
+
+    /// Parses what follows a `$`, leaving `rest` anywhere if it returns `None`.
+    fn placeholder(&mut self) -> Option<Part> {
+        let braced = self.eat("{");
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        let name = if digits.is_empty() { self.take_while(|c| c.is_ascii_alphanumeric() || c == '_') } else { "" };
+        if digits.is_empty() && !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            return None;
+        }
+        let part = |default, choices, transform| match digits.parse() {
+            Ok(index) => Part::TabStop { index, default, choices, transform },
+            Err(_) => Part::Variable { name: name.to_string(), default, transform },
+        };
+        if !braced {
+            return Some(part(Vec::new(), None, false));
+        }
+        if self.eat("}") {
+            return Some(part(Vec::new(), None, false));
+        }
+        if self.eat(":") {
+            let default = self.parts(true);
+            return if self.eat("}") { Some(part(default, None, false)) } else { None };
+        }
+        if !digits.is_empty() && self.eat("|") {
+            let mut choices = vec![String::new()];
+            loop {
+                let c = self.rest.chars().next()?;
+                self.rest = &self.rest[c.len_utf8()..];
+                match c {
+                    '\\' if self.rest.starts_with(['$', '}', '\\', ',', '|']) => {
+                        choices.last_mut().unwrap().push_str(&self.rest[..1]);
+                        self.rest = &self.rest[1..];
+                    }
+                    ',' => choices.push(String::new()),
+                    '|' if self.eat("}") => return Some(part(Vec::new(), Some(choices), false)),
+                    c => choices.last_mut().unwrap().push(c),
+                }
+            }
+        }
+        if self.eat("/") {
+            // /regex/format/options}, where the format may hold ${1:/upcase} and the like.
+            let mut slashes = 1;
+            let mut depth = 0;
+            while slashes < 3 {
+                let c = self.rest.chars().next()?;
+                self.rest = &self.rest[c.len_utf8()..];
+                match c {
+                    '\\' => {
+                        let escaped = self.rest.chars().next()?;
+                        self.rest = &self.rest[escaped.len_utf8()..];
+                    }
+                    '{' if slashes == 2 => depth += 1,
+                    '}' if depth > 0 => depth -= 1,
+                    '/' if depth == 0 => slashes += 1,
+                    _ => (),
+                }
+            }
+            self.take_while(|c| c.is_ascii_alphabetic());
+            return if self.eat("}") { Some(part(Vec::new(), None, true)) } else { None };
+        }
+        None
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        match self.rest.strip_prefix(s) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let len = self.rest.find(|c: char| !f(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        taken
+    }
+}
+
+impl VsSnippet {
+    /// The equivalent JetBrains template, named after the first prefix in the group
+    /// [`GROUP`], and notes on whatever couldn't be translated exactly.
+    ///
+    /// Tab stops become variables `VAR1`, `VAR2`, ... in tab stop order, `$0` becomes `$END$`,
+    /// choices become `enum()` and known VS Code variables become the matching functions.
+    pub fn to_template(&self) -> (Template, Vec<String>) {
+        let mut conversion = Conversion::default();
+        let value = conversion.convert(&parse_body(&self.body));
+        let mut variables = conversion.tab_stops;
+        variables.sort_by_key(|(index, _)| *index);
+        let mut variables: Vec<Variable> = variables.into_iter().map(|(_, v)| v).collect();
+        variables.extend(conversion.variables);
+
+        let mut notes = conversion.notes;
+        let mut contexts = Vec::new();
+        for scope in &self.scopes {
+            match SCOPES.iter().find(|(id, _)| id == scope) {
+                Some((_, context)) if !contexts.iter().any(|c: &ContextOption| c.name == *context) =>
+                    contexts.push(ContextOption { name: context.to_string(), value: true }),
+                Some(_) => (),
+                None => notes.push(format!("No template context for language {}", scope)),
+            }
+        }
+        if contexts.is_empty() {
+            contexts.push(ContextOption { name: "OTHER".to_string(), value: true });
+        }
+        if self.prefixes.len() > 1 {
+            notes.push(format!("Only the first of the prefixes {} is kept", self.prefixes.join(", ")));
+        }
+
+        let template = Template {
+            name: self.prefixes.first().cloned().unwrap_or_else(|| self.name.clone()),
+            value,
+            description: if self.description.is_empty() { self.name.clone() } else { self.description.clone() },
+            variables,
+            contexts,
+            group: GROUP.to_string(),
+            ..Default::default()
+        };
+        (template, notes)
+    }
+}
+
+/// State of converting one snippet body to a template value.
+#[derive(Default)]
+struct Conversion {
+    tab_stops: Vec<(usize, Variable)>,
+    variables: Vec<Variable>,
+    notes: Vec<String>,
+}
+
+impl Conversion {
+    fn convert(&mut self, parts: &[Part]) -> String {
+        let mut value = String::new();
+        for part in parts {
+            match part {
+                Part::Text(text) => value.push_str(&text.replace('$', "$$")),
+                Part::TabStop { index: 0, default, .. } => {
+                    if !default.is_empty() {
+                        self.notes.push("The placeholder text of $0 is dropped".to_string());
+                    }
+                    value.push_str("$END$");
+                }
+                Part::TabStop { index, default, choices, transform } => {
+                    let name = format!("VAR{}", index);
+                    if *transform {
+                        self.notes.push(format!("The transformation of ${} is dropped", index));
+                    }
+                    let variable = self.variable(default, &name, *index);
+                    let found = self.tab_stops.iter().position(|(i, _)| i == index);
+                    let found = match found {
+                        Some(found) => found,
+                        None => {
+                            self.tab_stops.push((*index, Variable { name: name.clone(), always_stop_at: true, ..Default::default() }));
+                            self.tab_stops.len() - 1
+                        }
+                    };
+                    let target = &mut self.tab_stops[found].1;
+                    if target.default_value.is_empty() {
+                        target.default_value = variable;
+                    }
+                    if let (Some(choices), true) = (choices, target.expression.is_empty()) {
+                        target.expression = JetBrainsEnum.emit(choices);
+                    }
+                    value.push_str(&format!("${}$", name));
+                }
+                Part::Variable { name, default, transform } => {
+                    if *transform {
+                        self.notes.push(format!("The transformation of ${} is dropped", name));
+                    }
+                    if name == "TM_SELECTED_TEXT" {
+                        value.push_str("$SELECTION$");
+                        continue;
+                    }
+                    if !self.variables.iter().any(|v| v.name == *name) {
+                        let variable = match VARIABLES.iter().find(|(_, n)| n == name) {
+                            Some((expression, _)) => Variable { name: name.clone(), expression: expression.to_string(), ..Default::default() },
+                            None => {
+                                self.notes.push(format!("Unknown variable ${} becomes a placeholder", name));
+                                let default_value = self.variable(default, name, 0);
+                                let default_value = if default_value.is_empty() { escape::quote(name) } else { default_value };
+                                Variable { name: name.clone(), default_value, always_stop_at: true, ..Default::default() }
+                            }
+                        };
+                        self.variables.push(variable);
+                    }
+                    value.push_str(&format!("${}$", name));
+                }
+            }
+        }
+        value
+    }
+
+    /// The default value expression for placeholder text, which can only be plain text.
+    fn variable(&mut self, default: &[Part], name: &str, index: usize) -> String {
+        if default.is_empty() {
+            return String::new();
+        }
+        let text: String = default.iter().map(|part| match part {
+            Part::Text(text) => text.clone(),
+            _ => String::new(),
+        }).collect();
+        if default.iter().any(|part| !matches!(part, Part::Text(_))) {
+            let what = if index > 0 { format!("${}", index) } else { format!("${}", name) };
+            self.notes.push(format!("Placeholders nested in {} are dropped", what));
+        }
+        escape::quote(&text)
+    }
+}
+
+impl VsSnippet {
+    /// The snippet's entry in a snippets file.
+    pub fn to_json(&self) -> Value {
+        let mut entry = json!({
+            "prefix": if self.prefixes.len() == 1 { Value::from(self.prefixes[0].clone()) } else { Value::from(self.prefixes.clone()) },
+            "body": self.body.split('\n').collect::<Vec<_>>(),
+        });
+        if !self.scopes.is_empty() {
+            entry["scope"] = self.scopes.join(",").into();
+        }
+        if !self.description.is_empty() {
+            entry["description"] = self.description.clone().into();
+        }
+        entry
+    }
+
+    /// The equivalent VS Code snippet and notes on whatever couldn't be translated exactly.
+    ///
+    /// Variables that stop for input become tab stops numbered in declaration order, `enum()`
+    /// becomes a choice, `$END$` `$0` and `$SELECTION$` `$TM_SELECTED_TEXT`. Functions with a
+    /// VS Code variable of the same meaning become that variable.
+    pub fn from_template(template: &Template) -> (VsSnippet, Vec<String>) {
+        let (body, mut notes) = fields::render(template, &Syntax);
+
+        let mut scopes: Vec<String> = Vec::new();
+        // OTHER, plain text and everything else, is what a snippet without a scope covers too.
+        for context in template.contexts.iter().filter(|c| c.value && c.name != "OTHER") {
+            match SCOPES.iter().find(|(_, c)| *c == context.name) {
+                Some((scope, _)) if !scopes.iter().any(|s| s == scope) => scopes.push(scope.to_string()),
+                Some(_) => (),
+                None => notes.push(format!("No VS Code language for context {}", context.name)),
+            }
+        }
+        if template.to_reformat {
+            notes.push("toReformat has no VS Code equivalent".to_string());
+        }
+
+        let snippet = VsSnippet {
+            name: if template.description.is_empty() { template.name.clone() } else { template.description.clone() },
+            prefixes: vec![template.name.clone()],
+            body,
+            description: template.description.clone(),
+            scopes,
+        };
+        (snippet, notes)
+    }
+}
+
+struct Syntax;
+
+impl fields::Syntax for Syntax {
+    fn text(&self, text: &str) -> String {
+        escape_text(text)
+    }
+
+    fn placeholder(&self, number: usize, default: &str) -> String {
+        format!("${{{}:{}}}", number, escape_text(default))
+    }
+
+    fn choice(&self, number: usize, choices: &[String], _: &mut Vec<String>) -> String {
+        Choice { tab_stop: number }.emit(choices)
+    }
+
+    fn computed(&self, expression: &Expr) -> Option<String> {
+        fields::lookup(VARIABLES, expression).map(|name| format!("${}", name))
+    }
+
+    fn selection(&self) -> String {
+        "$TM_SELECTED_TEXT".to_string()
+    }
+}
+
+/// A snippets file, keyed by snippet name; a name used twice gets its prefix added.
+pub fn to_file(snippets: &[VsSnippet]) -> String {
+    let mut file = Map::new();
+    for snippet in snippets {
+        let mut key = snippet.name.clone();
+        if file.contains_key(&key) {
+            key = format!("{} ({})", key, snippet.prefixes.join(", "));
+        }
+        file.insert(key, snippet.to_json());
+    }
+    format!("{:#}", Value::Object(file))
+}
+
+/// A `.code-snippets` file with a snippet for every template.
+pub struct Snippets;
+
+impl TemplateEmitter for Snippets {
+    fn emit_templates(&self, templates: &[Template]) -> String {
+        let snippets: Vec<VsSnippet> = templates.iter().map(|t| {
+            let (snippet, notes) = VsSnippet::from_template(t);
+            for note in notes {
+                warn!("Template \"{}\": {}", t.name, note);
+            }
+            snippet
+        }).collect();
+        to_file(&snippets)
+    }
+}
+
+/// Escapes text inside a choice, where `,` and `|` separate the options.
+pub fn escape_choice(text: &str) -> String {
+    fields::escape(text, &['\\', '$', '}', ',', '|'])
+}
+
+/// Escapes literal text in a snippet body, outside of any placeholder.
+pub fn escape_text(text: &str) -> String {
+    fields::escape(text, &['\\', '$', '}'])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test1() {
+        let items = vec!["a".to_string(), "b,c|d".to_string(), r"${x}\".to_string()];
+        assert_eq!(Choice::default().emit(&items), r"${1|a,b\,c\|d,\${x\}\\|}");
+        assert_eq!(Choice { tab_stop: 2 }.emit(&items[..1]), "${2|a|}");
+        assert_eq!(escape_text("$HOME {a|b}"), r"\$HOME {a|b\}");
+    }
+
+    #[test]
+    fn test2() {
+        let items = vec!["a".to_string(), "b".to_string()];
+        let snippet = Snippet { name: "Pick".to_string(), prefix: "pick".to_string(), description: String::new(), choice: Choice::default() };
+        assert_eq!(snippet.emit(&items), r#"{
+  "Pick": {
+    "prefix": "pick",
+    "body": [
+      "${1|a,b|}"
+    ]
+  }
+}"#);
+    }
+
+    #[test]
+    fn test3() {
+        let text = r#"{
+  // Line comment with "quotes"
+  "For loop": {
+    "prefix": ["for", "fori"],
+    "scope": "javascript,typescript,cobol",
+    "body": [
+      "for (let ${1:i} = 0; $1 < ${2|a,b\\,c|}; $1++) {",
+      "\t${0:// body} $$ \\$x ${TM_FILENAME/(.*)/${1:/upcase}/} $TM_SELECTED_TEXT $FOO",
+      "}",
+    ],
+    "description": "A /* not a comment */ loop",
+  },
+  /* block
+     comment */
+  "No body": { "prefix": "x" }
+}"#;
+        let snippets = read(text).unwrap();
+        assert_eq!(snippets.len(), 1);
+        let snippet = &snippets[0];
+        assert_eq!(snippet.prefixes, vec!["for", "fori"]);
+        assert_eq!(snippet.description, "A /* not a comment */ loop");
+        assert_eq!(snippet.scopes, vec!["javascript", "typescript", "cobol"]);
+
+        let (template, notes) = snippet.to_template();
+        assert_eq!(template.name, "for");
+        assert_eq!(template.value, "for (let $VAR1$ = 0; $VAR1$ < $VAR2$; $VAR1$++) {\n\t$END$ $$$$ $$x $TM_FILENAME$ $SELECTION$ $FOO$\n}");
+        let variables: Vec<(&str, &str, &str)> = template.variables.iter()
+            .map(|v| (v.name.as_str(), v.expression.as_str(), v.default_value.as_str()))
+            .collect();
+        assert_eq!(variables, vec![
+            ("VAR1", "", "\"i\""),
+            ("VAR2", "enum(\"a\", \"b,c\")", ""),
+            ("TM_FILENAME", "fileName()", ""),
+            ("FOO", "", "\"FOO\""),
+        ]);
+        assert_eq!(template.contexts.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["JAVA_SCRIPT", "TypeScript"]);
+        assert_eq!(notes, vec![
+            "The placeholder text of $0 is dropped",
+            "The transformation of $TM_FILENAME is dropped",
+            "Unknown variable $FOO becomes a placeholder",
+            "No template context for language cobol",
+            "Only the first of the prefixes for, fori is kept",
+        ]);
+    }
+
+    #[test]
+    fn test4() {
+        assert_eq!(parse_body("${1:a ${2:b}} $ ${x"), vec![
+            Part::TabStop {
+                index: 1,
+                default: vec![Part::Text("a ".to_string()), Part::TabStop { index: 2, default: vec![Part::Text("b".to_string())], choices: None, transform: false }],
+                choices: None,
+                transform: false,
+            },
+            Part::Text(" $ ${x".to_string()),
+        ]);
+        match read("{\n  \"a\": {\n    \"body\": 1 2\n}") {
+            Err(T2eError::Json { position, .. }) => assert_eq!(position, Position { line: 3, column: 15 }),
+            _ => panic!("expected a JSON error"),
+        }
+        assert!(is_snippets("// c\n{}") && !is_snippets("<templateSet/>"));
+    }
+
+    #[test]
+    fn test5() {
+        let xml = r#"<templateSet group="g">
+  <template name="sout" value="$TYPE$ $NAME$ = $SELECTION$; // costs $$5 {x}&#10;$NAME$ $FILE$ $G$ $UNDECLARED$$END$" description="Declare" toReformat="true">
+    <variable name="NAME" expression="" defaultValue="&quot;a}b&quot;" alwaysStopAt="true" />
+    <variable name="TYPE" expression="enum(&quot;int&quot;, &quot;a,b&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="FILE" expression="fileName()" defaultValue="" alwaysStopAt="false" />
+    <variable name="G" expression="groovyScript(&quot;1&quot;)" defaultValue="NAME" alwaysStopAt="false" />
+    <context>
+      <option name="JAVA_CODE" value="true" />
+      <option name="JAVA_STATEMENT" value="true" />
+    </context>
+  </template>
+</templateSet>"#;
+        let (templates, _) = crate::template::read(xml, true).unwrap();
+        let (snippet, notes) = VsSnippet::from_template(&templates[0]);
+        assert_eq!(snippet.body, "${2|int,a\\,b|} ${1:a\\}b} = $TM_SELECTED_TEXT; // costs \\$5 {x\\}\n$1 $TM_FILENAME ${3} ${4}$0");
+        assert_eq!(snippet.scopes, vec!["java"]);
+        assert_eq!(notes, vec![
+            "Expression groovyScript(\"1\") of G can't be translated",
+            "Default value NAME of G can't be translated",
+            "No VS Code language for context JAVA_STATEMENT",
+            "toReformat has no VS Code equivalent",
+        ]);
+        assert_eq!(to_file(std::slice::from_ref(&snippet)), r#"{
+  "Declare": {
+    "prefix": "sout",
+    "body": [
+      "${2|int,a\\,b|} ${1:a\\}b} = $TM_SELECTED_TEXT; // costs \\$5 {x\\}",
+      "$1 $TM_FILENAME ${3} ${4}$0"
+    ],
+    "scope": "java",
+    "description": "Declare"
+  }
+}"#);
+
+        let (back, _) = snippet.to_template();
+        assert_eq!(back.value, "$VAR2$ $VAR1$ = $SELECTION$; // costs $$5 {x}\n$VAR1$ $TM_FILENAME$ $VAR3$ $VAR4$$END$");
+        assert_eq!(back.variables[0].default_value, "\"a}b\"");
+        assert_eq!(back.variables[1].expression, "enum(\"int\", \"a,b\")");
+    }
+}
@@ -0,0 +1,144 @@
+//! Emacs yasnippet snippet directories, one subdirectory per major mode and one file per snippet.
+//!
+//! See <https://joaotavora.github.io/yasnippet/snippet-development.html>.
+
+use log::warn;
+use std::path::PathBuf;
+use crate::emit::FilesEmitter;
+use crate::expr::Expr;
+use crate::fields;
+use crate::template::Template;
+
+/// Template contexts and the Emacs major modes of the same languages.
+const MODES: &[(&str, &str)] = &[
+    ("JAVA_CODE", "java-mode"),
+    ("KOTLIN", "kotlin-mode"),
+    ("JAVA_SCRIPT", "js-mode"),
+    ("TypeScript", "typescript-mode"),
+    ("Python", "python-mode"),
+    ("SHELL_SCRIPT", "sh-mode"),
+    ("CSS", "css-mode"),
+    ("HTML", "html-mode"),
+    ("SQL", "sql-mode"),
+    ("GO", "go-mode"),
+    ("RUST_FILE", "rust-mode"),
+    ("Markdown", "markdown-mode"),
+    ("OTHER", "fundamental-mode"),
+];
+
+/// Template expressions and the Emacs Lisp computing the same value.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("fileName()", "(file-name-nondirectory (or (buffer-file-name) \"\"))"),
+    ("fileNameWithoutExtension()", "(file-name-base (or (buffer-file-name) \"\"))"),
+    ("filePath()", "(or (buffer-file-name) \"\")"),
+    ("lineNumber()", "(number-to-string (line-number-at-pos))"),
+    ("clipboard()", "(current-kill 0)"),
+    ("user()", "(user-login-name)"),
+    ("date(\"yyyy\")", "(format-time-string \"%Y\")"),
+    ("date(\"MM\")", "(format-time-string \"%m\")"),
+    ("date(\"dd\")", "(format-time-string \"%d\")"),
+    ("date(\"yyyy-MM-dd\")", "(format-time-string \"%Y-%m-%d\")"),
+    ("time(\"HH\")", "(format-time-string \"%H\")"),
+    ("time(\"mm\")", "(format-time-string \"%M\")"),
+    ("time(\"ss\")", "(format-time-string \"%S\")"),
+];
+
+/// A `MODE/NAME` snippet file for every mode each template is enabled in; templates enabled
+/// nowhere known go to `fundamental-mode`, whose snippets every mode offers.
+pub struct Yasnippet;
+
+impl FilesEmitter for Yasnippet {
+    fn emit_files(&self, templates: &[Template]) -> Vec<(PathBuf, String)> {
+        let mut files = Vec::new();
+        for (mode, templates) in fields::by_language(templates, MODES, "fundamental-mode") {
+            for (name, template) in fields::file_names(&templates).into_iter().zip(templates) {
+                files.push((PathBuf::from(mode).join(name), snippet(template)));
+            }
+        }
+        files
+    }
+}
+
+/// A snippet file, with the template's name as key and its group as yasnippet group.
+pub fn snippet(template: &Template) -> String {
+    let (body, notes) = fields::render(template, &Syntax);
+    for note in notes {
+        warn!("Template \"{}\": {}", template.name, note);
+    }
+
+    let mut header = String::from("# -*- mode: snippet -*-\n");
+    let name = if template.description.is_empty() { &template.name } else { &template.description };
+    header.push_str(&format!("# name: {}\n# key: {}\n", one_line(name), one_line(&template.name)));
+    if !template.group.is_empty() {
+        header.push_str(&format!("# group: {}\n", one_line(&template.group)));
+    }
+    format!("{}# --\n{}", header, body)
+}
+
+struct Syntax;
+
+impl fields::Syntax for Syntax {
+    fn text(&self, text: &str) -> String {
+        fields::escape(text, &['\\', '`', '$'])
+    }
+
+    fn placeholder(&self, number: usize, default: &str) -> String {
+        format!("${{{}:{}}}", number, fields::escape(default, &['\\', '`', '$', '}']))
+    }
+
+    fn choice(&self, number: usize, choices: &[String], _: &mut Vec<String>) -> String {
+        let choices: Vec<String> = choices.iter().map(|c| format!("\"{}\"", fields::escape(c, &['\\', '"']))).collect();
+        let choose = format!("$$(yas-choose-value '({}))", choices.join(" "));
+        format!("${{{}:{}}}", number, fields::escape(&choose, &['}']))
+    }
+
+    fn computed(&self, expression: &Expr) -> Option<String> {
+        fields::lookup(FUNCTIONS, expression).map(|f| format!("`{}`", f))
+    }
+
+    fn selection(&self) -> String {
+        "`(or yas-selected-text \"\")`".to_string()
+    }
+}
+
+fn one_line(text: &str) -> String {
+    text.replace(['\n', '\r'], " ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template;
+
+    #[test]
+    fn test1() {
+        let xml = r#"<templateSet group="sh">
+  <template name="for" value="for $X$ in `$F$`; do&#10;  $SELECTION$$END$ $$HOME&#10;done # $X$ $D$" description="Loop">
+    <variable name="X" expression="enum(&quot;a&quot;, &quot;b \&quot;c\&quot;&quot;)" defaultValue="" alwaysStopAt="true" />
+    <variable name="F" expression="fileName()" defaultValue="" alwaysStopAt="false" />
+    <variable name="D" expression="" defaultValue="&quot;x}&quot;" alwaysStopAt="true" />
+    <context>
+      <option name="SHELL_SCRIPT" value="true" />
+      <option name="CSS_DECLARATION_BLOCK" value="true" />
+    </context>
+  </template>
+  <template name="for" value="" description="">
+    <context><option name="SHELL_SCRIPT" value="true" /></context>
+  </template>
+  <template name="a/b" value="" description="" />
+</templateSet>"#;
+        let (templates, _) = template::read(xml, true).unwrap();
+        let files = Yasnippet.emit_files(&templates);
+        let paths: Vec<&str> = files.iter().map(|(p, _)| p.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["css-mode/for", "fundamental-mode/a_b", "sh-mode/for", "sh-mode/for-2"]);
+        assert_eq!(files[2].1, r#"# -*- mode: snippet -*-
+# name: Loop
+# key: for
+# group: sh
+# --
+for ${1:$$(yas-choose-value '("a" "b \"c\""))} in \``(file-name-nondirectory (or (buffer-file-name) ""))`\`; do
+  `(or yas-selected-text "")`$0 \$HOME
+done # $1 ${2:x\}}"#);
+    }
+}